@@ -0,0 +1,283 @@
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ops::Index,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+};
+
+#[cfg(debug_assertions)]
+use std::sync::atomic::AtomicU32;
+
+use crate::{Checkpoint, Idx};
+
+// NOTE: Entries are stored in buckets that double in capacity, where the
+// first bucket holds `1 << FIRST_BUCKET_BITS` entries. Buckets are never
+// moved or resized once allocated, which keeps references to entries
+// stable while other threads are allocating.
+const FIRST_BUCKET_BITS: u32 = 5;
+const BUCKETS: usize = (u32::BITS - FIRST_BUCKET_BITS + 1) as usize;
+
+fn locate(index: usize) -> (usize, usize) {
+    let shifted = index + (1 << FIRST_BUCKET_BITS);
+    let bucket = (usize::BITS - 1 - shifted.leading_zeros() - FIRST_BUCKET_BITS) as usize;
+    (bucket, shifted - bucket_capacity(bucket))
+}
+
+fn bucket_capacity(bucket: usize) -> usize {
+    1 << (bucket as u32 + FIRST_BUCKET_BITS)
+}
+
+struct Slot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+    initialized: AtomicBool,
+    #[cfg(debug_assertions)]
+    generation: AtomicU32,
+}
+
+impl<T> Slot<T> {
+    fn new() -> Self {
+        Self {
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            initialized: AtomicBool::new(false),
+            #[cfg(debug_assertions)]
+            generation: AtomicU32::new(0),
+        }
+    }
+
+    fn get(&self) -> Option<&T> {
+        if self.initialized.load(Ordering::Acquire) {
+            // SAFETY: initialized slots are never written to through `&self`.
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    fn clear(&mut self) {
+        if std::mem::replace(self.initialized.get_mut(), false) {
+            // SAFETY: the flag guarantees that the value was written.
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+impl<T> Drop for Slot<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// An append-only arena that can be allocated into through `&self`.
+///
+/// Allocation reserves an index atomically and writes into a bucket that
+/// is never moved, so entries can be read by other threads while more are
+/// being allocated.
+pub struct ConcurrentArena<T> {
+    buckets: [AtomicPtr<Slot<T>>; BUCKETS],
+    reserved: AtomicUsize,
+    #[cfg(all(debug_assertions, feature = "branded"))]
+    arena: u32,
+    #[cfg(debug_assertions)]
+    generation: u32,
+}
+
+// SAFETY: entries are only handed out as shared references, and slots are
+// only ever written to once before being published through `initialized`.
+unsafe impl<T: Send> Send for ConcurrentArena<T> {}
+unsafe impl<T: Send + Sync> Sync for ConcurrentArena<T> {}
+
+impl<T> ConcurrentArena<T> {
+    pub fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+            reserved: AtomicUsize::new(0),
+            #[cfg(all(debug_assertions, feature = "branded"))]
+            arena: crate::fresh_arena(),
+            #[cfg(debug_assertions)]
+            generation: 1,
+        }
+    }
+
+    pub fn allocate(&self, v: T) -> Idx<T> {
+        let index = self.reserved.fetch_add(1, Ordering::Relaxed);
+        assert!(
+            index <= u32::MAX as usize,
+            "Cannot allocate past the capacity of the arena."
+        );
+
+        let (bucket, offset) = locate(index);
+        // SAFETY: `offset` is within the capacity of `bucket`, and the slot
+        // was reserved exclusively for this call by the `fetch_add` above.
+        let slot = unsafe { &*self.bucket(bucket).add(offset) };
+        unsafe { (*slot.value.get()).write(v) };
+        #[cfg(debug_assertions)]
+        slot.generation.store(self.generation, Ordering::Relaxed);
+        slot.initialized.store(true, Ordering::Release);
+
+        #[cfg(debug_assertions)]
+        {
+            Idx::tagged(
+                index as u32,
+                #[cfg(feature = "branded")]
+                self.arena,
+                self.generation,
+            )
+        }
+        #[cfg(not(debug_assertions))]
+        Idx::new(index as u32)
+    }
+
+    /// Records the number of allocations reserved so far, such that any
+    /// allocations made after this point can be discarded.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            length: self.reserved.load(Ordering::Acquire),
+            #[cfg(all(debug_assertions, feature = "branded"))]
+            arena: self.arena,
+        }
+    }
+
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        #[cfg(all(debug_assertions, feature = "branded"))]
+        assert_eq!(
+            checkpoint.arena, self.arena,
+            "Cannot rollback to a checkpoint from a different arena."
+        );
+        let length = *self.reserved.get_mut();
+        assert!(
+            checkpoint.length <= length,
+            "Cannot rollback to a checkpoint past the end of the arena."
+        );
+        for index in checkpoint.length..length {
+            if let Some(slot) = self.slot_mut(index) {
+                slot.clear();
+            }
+        }
+        *self.reserved.get_mut() = checkpoint.length;
+        #[cfg(debug_assertions)]
+        {
+            self.generation += 1;
+        }
+    }
+
+    /// The number of allocations reserved so far, which includes the ones
+    /// that are still being written to by other threads.
+    pub fn len(&self) -> usize {
+        self.reserved.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: Idx<T>) -> Option<&T> {
+        let slot = self.slot(index.value as usize)?;
+        #[cfg(debug_assertions)]
+        if self.validate(index, slot).is_err() {
+            return None;
+        }
+        slot.get()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Idx<T>, &T)> {
+        (0..self.len()).filter_map(|index| {
+            let slot = self.slot(index)?;
+            let entry = slot.get()?;
+            #[cfg(debug_assertions)]
+            let index = Idx::tagged(
+                index as u32,
+                #[cfg(feature = "branded")]
+                self.arena,
+                slot.generation.load(Ordering::Relaxed),
+            );
+            #[cfg(not(debug_assertions))]
+            let index = Idx::new(index as u32);
+            Some((index, entry))
+        })
+    }
+
+    fn bucket(&self, bucket: usize) -> *mut Slot<T> {
+        let slots = self.buckets[bucket].load(Ordering::Acquire);
+        if !slots.is_null() {
+            return slots;
+        }
+
+        let capacity = bucket_capacity(bucket);
+        let fresh: Box<[Slot<T>]> = (0..capacity).map(|_| Slot::new()).collect();
+        let fresh = Box::into_raw(fresh) as *mut Slot<T>;
+
+        match self.buckets[bucket].compare_exchange(
+            ptr::null_mut(),
+            fresh,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => fresh,
+            Err(slots) => {
+                // SAFETY: another thread won the race, so `fresh` is unshared.
+                drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(fresh, capacity)) });
+                slots
+            }
+        }
+    }
+
+    fn slot(&self, index: usize) -> Option<&Slot<T>> {
+        let (bucket, offset) = locate(index);
+        let slots = self.buckets.get(bucket)?.load(Ordering::Acquire);
+        // SAFETY: allocated buckets live as long as the arena does.
+        (!slots.is_null()).then(|| unsafe { &*slots.add(offset) })
+    }
+
+    fn slot_mut(&mut self, index: usize) -> Option<&mut Slot<T>> {
+        let (bucket, offset) = locate(index);
+        let slots = *self.buckets.get_mut(bucket)?.get_mut();
+        // SAFETY: allocated buckets live as long as the arena does.
+        (!slots.is_null()).then(|| unsafe { &mut *slots.add(offset) })
+    }
+
+    #[cfg(debug_assertions)]
+    fn validate(&self, index: Idx<T>, slot: &Slot<T>) -> Result<(), &'static str> {
+        #[cfg(feature = "branded")]
+        if index.arena != 0 && index.arena != self.arena {
+            return Err("Attempted to use an index from a different arena");
+        }
+        if index.generation != 0 && slot.generation.load(Ordering::Relaxed) != index.generation {
+            return Err("Attempted to use an index that was discarded by a rollback");
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for ConcurrentArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ConcurrentArena<T> {
+    fn drop(&mut self) {
+        for (bucket, slots) in self.buckets.iter_mut().enumerate() {
+            let slots = *slots.get_mut();
+            if !slots.is_null() {
+                let capacity = bucket_capacity(bucket);
+                // SAFETY: the bucket was allocated as a boxed slice of `capacity`.
+                drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(slots, capacity)) });
+            }
+        }
+    }
+}
+
+impl<T> Index<Idx<T>> for ConcurrentArena<T> {
+    type Output = T;
+
+    fn index(&self, index: Idx<T>) -> &Self::Output {
+        let slot = self.slot(index.value as usize);
+        #[cfg(debug_assertions)]
+        if let Some(Err(message)) = slot.map(|slot| self.validate(index, slot)) {
+            panic!("{}: {:?}", message, index);
+        }
+        slot.and_then(Slot::get)
+            .unwrap_or_else(|| panic!("Attempted to use an unallocated index: {:?}", index))
+    }
+}
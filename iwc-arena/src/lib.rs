@@ -1,32 +1,152 @@
-use std::{marker::PhantomData, ops::Index};
+pub mod concurrent;
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hash, Hasher},
+    marker::PhantomData,
+    ops::Index,
+    sync::Mutex,
+};
+
+#[cfg(all(debug_assertions, feature = "branded"))]
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use concurrent::ConcurrentArena;
+
+// NOTE: With the `branded` feature, debug builds brand each arena with a
+// unique identifier, which is stored alongside the generation in every
+// index that it allocates. This allows indices from a different arena to
+// be caught early, while generations catch indices used after rollbacks.
+#[cfg(all(debug_assertions, feature = "branded"))]
+static FRESH_ARENA: AtomicU32 = AtomicU32::new(1);
+
+#[cfg(all(debug_assertions, feature = "branded"))]
+fn fresh_arena() -> u32 {
+    FRESH_ARENA.fetch_add(1, Ordering::Relaxed)
+}
 
 pub struct Arena<T> {
     entries: Vec<T>,
+    #[cfg(all(debug_assertions, feature = "branded"))]
+    arena: u32,
+    #[cfg(debug_assertions)]
+    generations: Vec<u32>,
+    #[cfg(debug_assertions)]
+    generation: u32,
 }
 
 impl<T> Arena<T> {
     pub fn new() -> Self {
         Self {
             entries: Vec::new(),
+            #[cfg(all(debug_assertions, feature = "branded"))]
+            arena: fresh_arena(),
+            #[cfg(debug_assertions)]
+            generations: Vec::new(),
+            #[cfg(debug_assertions)]
+            generation: 1,
         }
     }
 
     pub fn allocate(&mut self, v: T) -> Idx<T> {
         let index = self.current_index();
         self.entries.push(v);
-        Idx::new(index as u32)
+        #[cfg(debug_assertions)]
+        self.generations.push(self.generation);
+        self.index_at(index)
+    }
+
+    /// Records the current length of the arena, such that allocations
+    /// made after this point can be discarded through [`Arena::rollback`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            length: self.current_index(),
+            #[cfg(all(debug_assertions, feature = "branded"))]
+            arena: self.arena,
+        }
+    }
+
+    /// Discards every allocation made since the `checkpoint` was taken.
+    ///
+    /// Indices pointing to discarded allocations must not be used again;
+    /// debug builds panic if they're used to index the arena.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        #[cfg(all(debug_assertions, feature = "branded"))]
+        assert_eq!(
+            checkpoint.arena, self.arena,
+            "Cannot rollback to a checkpoint from a different arena."
+        );
+        assert!(
+            checkpoint.length <= self.current_index(),
+            "Cannot rollback to a checkpoint past the end of the arena."
+        );
+        self.entries.truncate(checkpoint.length);
+        #[cfg(debug_assertions)]
+        {
+            self.generations.truncate(checkpoint.length);
+            self.generation += 1;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: Idx<T>) -> Option<&T> {
+        #[cfg(debug_assertions)]
+        if self.validate(index).is_err() {
+            return None;
+        }
+        self.entries.get(index.value as usize)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Idx<T>, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (self.index_at(index), entry))
     }
 
     fn current_index(&self) -> usize {
         self.entries.len()
     }
+
+    fn index_at(&self, index: usize) -> Idx<T> {
+        #[cfg(debug_assertions)]
+        {
+            Idx::tagged(
+                index as u32,
+                #[cfg(feature = "branded")]
+                self.arena,
+                self.generations[index],
+            )
+        }
+        #[cfg(not(debug_assertions))]
+        Idx::new(index as u32)
+    }
+
+    #[cfg(debug_assertions)]
+    fn validate(&self, index: Idx<T>) -> Result<(), &'static str> {
+        #[cfg(feature = "branded")]
+        if index.arena != 0 && index.arena != self.arena {
+            return Err("Attempted to use an index from a different arena");
+        }
+        if index.generation != 0
+            && self.generations.get(index.value as usize) != Some(&index.generation)
+        {
+            return Err("Attempted to use an index that was discarded by a rollback");
+        }
+        Ok(())
+    }
 }
 
 impl<T> Default for Arena<T> {
     fn default() -> Self {
-        Self {
-            entries: Vec::new(),
-        }
+        Self::new()
     }
 }
 
@@ -34,12 +154,244 @@ impl<T> Index<Idx<T>> for Arena<T> {
     type Output = T;
 
     fn index(&self, index: Idx<T>) -> &Self::Output {
+        #[cfg(debug_assertions)]
+        if let Err(message) = self.validate(index) {
+            panic!("{}: {:?}", message, index);
+        }
         &self.entries[index.value as usize]
     }
 }
 
+const INTERNED_SHARDS: usize = 16;
+
+/// A concurrent arena that returns the same index for equal values.
+///
+/// The interning table is split into shards by hash, such that threads
+/// allocating unrelated values rarely contend for the same lock.
+pub struct InternedArena<T> {
+    arena: ConcurrentArena<T>,
+    // NOTE: Values are only stored in the arena, while the interning table
+    // maps their hashes to the indices that share them; collisions are
+    // resolved by comparing against the entries in the arena.
+    interned: [Mutex<HashMap<u64, Vec<Idx<T>>>>; INTERNED_SHARDS],
+    hasher: RandomState,
+}
+
+impl<T: Eq + Hash> InternedArena<T> {
+    pub fn new() -> Self {
+        Self {
+            arena: ConcurrentArena::new(),
+            interned: std::array::from_fn(|_| Mutex::new(HashMap::new())),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Allocates `v`, returning the index of a previous allocation if an
+    /// equal value was already allocated in the arena.
+    pub fn allocate(&self, v: T) -> Idx<T> {
+        let hash = self.hasher.hash_one(&v);
+        let mut interned = self.interned[hash as usize % INTERNED_SHARDS]
+            .lock()
+            .unwrap();
+        let indices = interned.entry(hash).or_default();
+        if let Some(index) = indices.iter().find(|index| self.arena[**index] == v) {
+            return *index;
+        }
+        let index = self.arena.allocate(v);
+        indices.push(index);
+        index
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.arena.checkpoint()
+    }
+
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        let discarded: Vec<_> = self
+            .arena
+            .iter()
+            .skip(checkpoint.length)
+            .map(|(index, entry)| (index, self.hasher.hash_one(entry)))
+            .collect();
+        for (index, hash) in discarded {
+            let interned = self.interned[hash as usize % INTERNED_SHARDS]
+                .get_mut()
+                .unwrap();
+            if let Some(indices) = interned.get_mut(&hash) {
+                indices.retain(|interned| *interned != index);
+                if indices.is_empty() {
+                    interned.remove(&hash);
+                }
+            }
+        }
+        self.arena.rollback(checkpoint);
+    }
+
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    pub fn get(&self, index: Idx<T>) -> Option<&T> {
+        self.arena.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Idx<T>, &T)> {
+        self.arena.iter()
+    }
+}
+
+impl<T: Eq + Hash> Default for InternedArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<Idx<T>> for InternedArena<T> {
+    type Output = T;
+
+    fn index(&self, index: Idx<T>) -> &Self::Output {
+        &self.arena[index]
+    }
+}
+
+/// A side table keyed by the indices of an arena.
+///
+/// With the `branded` feature, a side table takes the brand of the first
+/// index inserted into it, and debug builds panic if it's later used with
+/// an index from a different arena. Indices created through [`Idx::new`]
+/// carry no brand, so they're accepted by any side table.
+pub struct ArenaMap<K, V> {
+    entries: Vec<Option<V>>,
+    #[cfg(all(debug_assertions, feature = "branded"))]
+    arena: u32,
+    _marker: PhantomData<fn() -> K>,
+}
+
+impl<T, V> ArenaMap<Idx<T>, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            #[cfg(all(debug_assertions, feature = "branded"))]
+            arena: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, index: Idx<T>, v: V) -> Option<V> {
+        #[cfg(all(debug_assertions, feature = "branded"))]
+        {
+            self.validate(index);
+            if self.arena == 0 {
+                self.arena = index.arena;
+            }
+        }
+        let index = index.value as usize;
+        if index >= self.entries.len() {
+            self.entries.resize_with(index + 1, || None);
+        }
+        self.entries[index].replace(v)
+    }
+
+    pub fn remove(&mut self, index: Idx<T>) -> Option<V> {
+        #[cfg(all(debug_assertions, feature = "branded"))]
+        self.validate(index);
+        self.entries.get_mut(index.value as usize)?.take()
+    }
+
+    pub fn get(&self, index: Idx<T>) -> Option<&V> {
+        #[cfg(all(debug_assertions, feature = "branded"))]
+        self.validate(index);
+        self.entries.get(index.value as usize)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, index: Idx<T>) -> Option<&mut V> {
+        #[cfg(all(debug_assertions, feature = "branded"))]
+        self.validate(index);
+        self.entries.get_mut(index.value as usize)?.as_mut()
+    }
+
+    pub fn contains_key(&self, index: Idx<T>) -> bool {
+        self.get(index).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Idx<T>, &V)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((Idx::new(index as u32), entry.as_ref()?)))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().flatten()
+    }
+
+    #[cfg(all(debug_assertions, feature = "branded"))]
+    fn validate(&self, index: Idx<T>) {
+        assert!(
+            self.arena == 0 || index.arena == 0 || self.arena == index.arena,
+            "Attempted to use an index from a different arena: {:?}",
+            index
+        );
+    }
+}
+
+impl<T, V> Default for ArenaMap<Idx<T>, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, V> Index<Idx<T>> for ArenaMap<Idx<T>, V> {
+    type Output = V;
+
+    fn index(&self, index: Idx<T>) -> &Self::Output {
+        self.get(index).expect("No entry found for index.")
+    }
+}
+
+impl<K, V: Clone> Clone for ArenaMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            #[cfg(all(debug_assertions, feature = "branded"))]
+            arena: self.arena,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V: std::fmt::Debug> std::fmt::Debug for ArenaMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.entries
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, entry)| Some((index, entry.as_ref()?))),
+            )
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    length: usize,
+    #[cfg(all(debug_assertions, feature = "branded"))]
+    arena: u32,
+}
+
 pub struct Idx<T> {
     value: u32,
+    // NOTE: Indices created through `Idx::new` have an arena and generation
+    // of `0`, which opts them out of the checks done by the arena.
+    #[cfg(all(debug_assertions, feature = "branded"))]
+    arena: u32,
+    #[cfg(debug_assertions)]
+    generation: u32,
     _marker: PhantomData<fn() -> T>,
 }
 
@@ -47,6 +399,29 @@ impl<T> Idx<T> {
     pub fn new(value: u32) -> Self {
         Self {
             value,
+            #[cfg(all(debug_assertions, feature = "branded"))]
+            arena: 0,
+            #[cfg(debug_assertions)]
+            generation: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn from_raw(value: u32) -> Self {
+        Self::new(value)
+    }
+
+    pub fn into_raw(self) -> u32 {
+        self.value
+    }
+
+    #[cfg(debug_assertions)]
+    fn tagged(value: u32, #[cfg(feature = "branded")] arena: u32, generation: u32) -> Self {
+        Self {
+            value,
+            #[cfg(feature = "branded")]
+            arena,
+            generation,
             _marker: PhantomData,
         }
     }
@@ -69,11 +444,164 @@ impl<T> std::fmt::Debug for Idx<T> {
     }
 }
 
+// NOTE: Equality, ordering, and hashing only consider the position of the
+// index, as the brand is bookkeeping for the checks done in debug builds.
+
+impl<T> PartialEq for Idx<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T> Eq for Idx<T> {}
+
+impl<T> Hash for Idx<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl<T> PartialOrd for Idx<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Idx<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
 impl<T> Default for Idx<T> {
     fn default() -> Self {
-        Self {
-            value: u32::default(),
-            _marker: PhantomData,
+        Self::new(u32::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{concurrent::ConcurrentArena, Arena, ArenaMap, InternedArena};
+
+    #[test]
+    fn rollback_discards_allocations() {
+        let mut arena = Arena::default();
+
+        let zero = arena.allocate(0);
+        let checkpoint = arena.checkpoint();
+        arena.allocate(1);
+        arena.allocate(2);
+        arena.rollback(checkpoint);
+
+        let three = arena.allocate(3);
+
+        assert_eq!(arena[zero], 0);
+        assert_eq!(arena[three], 3);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "discarded by a rollback")]
+    fn rollback_invalidates_indices() {
+        let mut arena = Arena::default();
+
+        let checkpoint = arena.checkpoint();
+        let stale = arena.allocate(0);
+        arena.rollback(checkpoint);
+        arena.allocate(1);
+
+        let _ = arena[stale];
+    }
+
+    #[test]
+    fn interning_shares_indices() {
+        let mut arena = InternedArena::default();
+
+        let zero = arena.allocate(0);
+        let one = arena.allocate(1);
+
+        assert_eq!(arena.allocate(0), zero);
+        assert_ne!(zero, one);
+
+        let checkpoint = arena.checkpoint();
+        arena.allocate(2);
+        arena.rollback(checkpoint);
+
+        assert_eq!(arena.allocate(1), one);
+        let two = arena.allocate(2);
+        assert_eq!(arena[two], 2);
+    }
+
+    #[test]
+    fn arena_map_side_table() {
+        let mut arena = Arena::default();
+        let mut names = ArenaMap::default();
+
+        let zero = arena.allocate(0);
+        let one = arena.allocate(1);
+        let two = arena.allocate(2);
+
+        assert_eq!(names.insert(two, "two"), None);
+        assert_eq!(names.insert(zero, "zero"), None);
+        assert_eq!(names.insert(zero, "nil"), Some("zero"));
+
+        assert_eq!(names.get(one), None);
+        assert_eq!(names[two], "two");
+        assert_eq!(
+            names.iter().collect::<Vec<_>>(),
+            vec![(zero, &"nil"), (two, &"two")]
+        );
+
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.iter().map(|(_, v)| *v).sum::<i32>(), 3);
+        assert_eq!(arena.get(super::Idx::from_raw(3)), None);
+        assert_eq!(one.into_raw(), 1);
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "branded"))]
+    #[should_panic(expected = "from a different arena")]
+    fn arenas_reject_foreign_indices() {
+        let mut arena = Arena::default();
+        let mut other = Arena::default();
+
+        arena.allocate(0);
+        let foreign = other.allocate(1);
+
+        let _ = arena[foreign];
+    }
+
+    #[test]
+    fn concurrent_allocation() {
+        let mut arena = ConcurrentArena::default();
+
+        let indices: Vec<_> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|worker| {
+                    let arena = &arena;
+                    scope.spawn(move || {
+                        (0..1000)
+                            .map(|value| {
+                                (arena.allocate(worker * 1000 + value), worker * 1000 + value)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(arena.len(), 4000);
+        for (index, value) in &indices {
+            assert_eq!(arena[*index], *value);
         }
+
+        let checkpoint = arena.checkpoint();
+        arena.allocate(4000);
+        arena.rollback(checkpoint);
+        assert_eq!(arena.iter().count(), 4000);
     }
 }
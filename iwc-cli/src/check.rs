@@ -0,0 +1,205 @@
+use std::fmt::Write;
+
+use iwc_core_ast::ty::pretty::{Naming, PrettyOptions, TypePrinter};
+use iwc_core_constraint::Constraint;
+use iwc_core_elaborate::{context::Context, declare::Declare, entail::Evidence, infer::Infer};
+use iwc_parser::{parse_module, Arenas};
+
+use crate::diagnostic::{sorted, Diagnostics, Sources};
+
+#[derive(Debug, Default)]
+pub(crate) struct Dumps {
+    pub(crate) constraints: bool,
+    pub(crate) solutions: bool,
+    pub(crate) evidence: bool,
+}
+
+/// The output of checking a file, which only lists the types of bindings
+/// when there are no diagnostics.
+#[derive(Debug, Default)]
+pub(crate) struct Report {
+    pub(crate) output: String,
+    pub(crate) diagnostics: Vec<String>,
+}
+
+pub(crate) fn check(file: &str, source: &str, dumps: &Dumps) -> Report {
+    let mut context = Context::default();
+    let mut report = Report::default();
+    let sources = Sources::from([(file.into(), source.into())]);
+
+    let (expr_arena, type_arena, expr_spans, type_spans) = context.arenas_mut();
+    let (module, errors) = parse_module(
+        file,
+        source,
+        Arenas {
+            expr_arena,
+            type_arena,
+            expr_spans,
+            type_spans,
+        },
+    );
+    if !errors.is_empty() {
+        let diagnostics = Diagnostics::new(&sources, &context);
+        report.diagnostics = errors
+            .iter()
+            .map(|error| diagnostics.parse_error(error).to_string())
+            .collect();
+        return report;
+    }
+
+    if dumps.constraints {
+        context.record_constraints();
+    }
+
+    let mut declare = Declare::new(&mut context);
+    let mut kind_errors = vec![];
+    for data in &module.data {
+        if let Err(error) = declare.declare_data(data) {
+            kind_errors.push(error);
+        }
+    }
+    for class in &module.classes {
+        declare.declare_class(class);
+    }
+    for instance in &module.instances {
+        if let Err(error) = declare.declare_instance(instance) {
+            kind_errors.push(error);
+        }
+    }
+
+    let mut infer = Infer::new(&mut context);
+    let result = infer.infer_module(&module);
+    infer.to_solve().solve();
+
+    let diagnostics = Diagnostics::new(&sources, &context);
+    dump(&context, &diagnostics, dumps, &mut report.output);
+
+    let mut errors: Vec<_> = kind_errors
+        .iter()
+        .chain(context.kind_errors())
+        .map(|error| diagnostics.kind_error(error))
+        .collect();
+    errors.extend(
+        context
+            .infer_errors()
+            .iter()
+            .map(|error| diagnostics.infer_error(error)),
+    );
+    if let Err(error) = result {
+        errors.push(diagnostics.error(None, &error.to_string()));
+    }
+    errors.extend(
+        context
+            .pattern_errors()
+            .iter()
+            .map(|error| diagnostics.pattern_error(error)),
+    );
+    errors.extend(
+        context
+            .solutions()
+            .unification_errors()
+            .iter()
+            .map(|(error, origin)| diagnostics.unify_error(error, *origin)),
+    );
+    errors.extend(
+        context
+            .solutions()
+            .entailment_deferred()
+            .iter()
+            .map(|deferred| diagnostics.unsolved(deferred)),
+    );
+    report.diagnostics = sorted(errors)
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+    if !report.diagnostics.is_empty() {
+        return report;
+    }
+
+    let options = PrettyOptions {
+        naming: Naming::Letters,
+        ..PrettyOptions::default()
+    };
+    for value in &module.values {
+        if let Some(t_idx) = context.environment().value(&value.name) {
+            let ty = TypePrinter::new(context.type_arena(), options).print_ty(t_idx);
+            writeln!(report.output, "{} :: {}", value.name, ty).unwrap();
+        }
+    }
+
+    report
+}
+
+/// Dumps are printed in the internal notation for types, where unification
+/// variables keep their names.
+fn dump(context: &Context, diagnostics: &Diagnostics, dumps: &Dumps, output: &mut String) {
+    let options = PrettyOptions {
+        naming: Naming::Internal,
+        ..PrettyOptions::flat()
+    };
+    let mut printer = TypePrinter::new(context.type_arena(), options);
+
+    if dumps.constraints {
+        writeln!(output, "-- constraints").unwrap();
+        for constraint in context.recorded_constraints() {
+            match constraint {
+                Constraint::ClassEntail(index, assertion, _) => writeln!(
+                    output,
+                    "entail #{}: {}",
+                    index,
+                    printer.print_assertion(assertion)
+                ),
+                Constraint::UnifyDeep(t_name, u_name, _) => {
+                    writeln!(output, "unify ?{} ~ ?{}", t_name, u_name)
+                }
+                Constraint::UnifySolve(t_name, u_idx, _) => {
+                    writeln!(output, "solve ?{} := {}", t_name, printer.print_ty(*u_idx))
+                }
+                Constraint::UnifyError(error, origin) => {
+                    writeln!(output, "{}", diagnostics.unify_error(error, *origin))
+                }
+            }
+            .unwrap();
+        }
+    }
+
+    if dumps.solutions {
+        writeln!(output, "-- solutions").unwrap();
+        let substitution = context.solutions().substitution();
+        let mut names: Vec<_> = substitution.names().collect();
+        names.sort();
+        for name in names {
+            match substitution.solution(name) {
+                Some(t_idx) => writeln!(output, "?{} := {}", name, printer.print_ty(t_idx)),
+                None => writeln!(output, "?{} := ?{}", name, substitution.find(name)),
+            }
+            .unwrap();
+        }
+    }
+
+    if dumps.evidence {
+        writeln!(output, "-- evidence").unwrap();
+        let mut evidences: Vec<_> = context.solutions().entailment_evidences().iter().collect();
+        evidences.sort_by_key(|(index, _)| **index);
+        for (index, evidence) in evidences {
+            match evidence {
+                Evidence::Dictionary { dependencies } => {
+                    let dependencies: Vec<_> = dependencies
+                        .iter()
+                        .map(|dependency| format!("#{}", dependency))
+                        .collect();
+                    writeln!(
+                        output,
+                        "#{}: dictionary [{}]",
+                        index,
+                        dependencies.join(", ")
+                    )
+                }
+                Evidence::Given { index: given } => {
+                    writeln!(output, "#{}: given #{}", index, given)
+                }
+            }
+            .unwrap();
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::{collections::HashMap, fmt};
+
+use iwc_core_ast::{
+    expr::{pretty::pretty_print_pattern, ExprIdx},
+    kind::KindPrinter,
+    span::Span,
+    ty::{
+        pretty::{Naming, PrettyOptions, TypePrinter},
+        Type, TypeIdx,
+    },
+};
+use iwc_core_elaborate::{context::Context, solve::DeferredAssertion, zonk::Zonk};
+use iwc_core_error::{InferError, KindError, ParseError, PatternError, UnifyError};
+use smol_str::SmolStr;
+
+/// Source files by name, for looking up the lines and columns of spans.
+pub(crate) type Sources = HashMap<SmolStr, String>;
+
+/// An error rendered for display, along with the span it's reported at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Diagnostic {
+    span: Option<Span>,
+    message: String,
+    rendered: String,
+}
+
+impl Diagnostic {
+    /// Where the diagnostic is reported, by its file and where it starts.
+    fn location(&self) -> Option<(&SmolStr, usize)> {
+        self.span.as_ref().map(|span| (&span.file, span.start))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rendered)
+    }
+}
+
+/// Orders diagnostics by where they're reported, dropping the duplicates
+/// from expressions that start at the same place, such as an annotation
+/// and the expression it annotates. The ones without a span come last.
+pub(crate) fn sorted(mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics.sort_by(|a, b| {
+        (a.span.is_none(), a.location(), &a.message).cmp(&(
+            b.span.is_none(),
+            b.location(),
+            &b.message,
+        ))
+    });
+    diagnostics.dedup_by(|a, b| (a.location(), &a.message) == (b.location(), &b.message));
+    diagnostics
+}
+
+/// Renders errors as `file:line:column: error: message`, where the types in
+/// each message are zonked and share their names for unification variables.
+pub(crate) struct Diagnostics<'a> {
+    sources: &'a Sources,
+    context: &'a Context,
+}
+
+impl<'a> Diagnostics<'a> {
+    pub(crate) fn new(sources: &'a Sources, context: &'a Context) -> Self {
+        Self { sources, context }
+    }
+
+    pub(crate) fn parse_error(&self, error: &ParseError) -> Diagnostic {
+        let message = match error {
+            ParseError::UnexpectedCharacter(_, character) => {
+                format!("unexpected character {:?}", character)
+            }
+            ParseError::UnterminatedLiteral(_) => "unterminated literal".into(),
+            ParseError::InvalidLiteral(_) => "invalid literal".into(),
+            ParseError::UnexpectedToken(_, expected, found) => {
+                format!("expected {}, found {}", expected, found)
+            }
+            ParseError::MissingBinding(_, name) => {
+                format!("the signature for `{}` lacks a binding", name)
+            }
+            ParseError::DuplicateSignature(_, name) => {
+                format!("`{}` already has a signature", name)
+            }
+            ParseError::DuplicateBinding(_, name) => {
+                format!("`{}` is already bound", name)
+            }
+            ParseError::UnknownParameter(_, name) => {
+                format!("`{}` is not a parameter of the class", name)
+            }
+        };
+        self.error(Some(error.span()), &message)
+    }
+
+    /// Errors are reported at the expression they arose from. Types are
+    /// interned, so their spans can't stand in for the ones that arose
+    /// elsewhere, and those are reported without a location.
+    pub(crate) fn unify_error(&self, error: &UnifyError, origin: Option<ExprIdx>) -> Diagnostic {
+        let mut printer = self.printer();
+        let message = match error {
+            UnifyError::CannotUnify(t_idx, u_idx) => format!(
+                "cannot unify `{}` with `{}`",
+                self.print_ty(&mut printer, *t_idx),
+                self.print_ty(&mut printer, *u_idx)
+            ),
+            UnifyError::ImpredicativeType(_, t_idx) => format!(
+                "cannot instantiate a type variable to the polymorphic type `{}`",
+                self.print_ty(&mut printer, *t_idx)
+            ),
+            UnifyError::InfiniteType(name, t_idx) | UnifyError::InfiniteRow(name, t_idx) => {
+                let variable = self.unification(*name);
+                format!(
+                    "`{}` occurs in `{}`, making it infinite",
+                    self.print_ty(&mut printer, variable),
+                    self.print_ty(&mut printer, *t_idx)
+                )
+            }
+            UnifyError::DuplicateLabel(t_idx, label) => format!(
+                "duplicate label `{}` in `{}`",
+                label,
+                self.print_ty(&mut printer, *t_idx)
+            ),
+            UnifyError::PartialSynonym(t_idx, arity, given) => {
+                partial_synonym(&self.print_ty(&mut printer, *t_idx), *arity, *given)
+            }
+        };
+        let span = origin.and_then(|e_idx| self.context.expr_span(e_idx));
+        self.error(span.as_ref(), &message)
+    }
+
+    /// Errors are reported where the type at fault was written, as the type
+    /// alone could've been written in several places.
+    pub(crate) fn kind_error(&self, error: &KindError) -> Diagnostic {
+        let mut printer = self.printer();
+        let mut kinds = KindPrinter::default();
+        let message = match error {
+            KindError::CannotUnify(_, t_idx, t_kind, u_kind) => format!(
+                "cannot unify the kind `{}` with `{}` in `{}`",
+                kinds.print_kind(t_kind),
+                kinds.print_kind(u_kind),
+                self.print_ty(&mut printer, *t_idx)
+            ),
+            KindError::InfiniteKind(_, t_idx, _, kind) => format!(
+                "the kind of `{}` would be infinite, as in `{}`",
+                self.print_ty(&mut printer, *t_idx),
+                kinds.print_kind(kind)
+            ),
+            KindError::UnknownConstructor(_, _, name) => {
+                format!("unknown type constructor `{}`", name)
+            }
+            KindError::PartialSynonym(_, t_idx, arity, given) => {
+                partial_synonym(&self.print_ty(&mut printer, *t_idx), *arity, *given)
+            }
+            KindError::CyclicSynonym(_, _, name) => {
+                format!("the synonym `{}` refers to itself", name)
+            }
+            KindError::UnknownClass(_, name) => format!("unknown class `{}`", name),
+            KindError::ClassArity(_, name, arity, given) => format!(
+                "the class `{}` expects {} arguments but was given {}",
+                name, arity, given
+            ),
+        };
+        self.error(error.span(), &message)
+    }
+
+    pub(crate) fn pattern_error(&self, error: &PatternError) -> Diagnostic {
+        let (e_idx, message) = match error {
+            PatternError::NonExhaustive(e_idx, missing) => {
+                let missing: Vec<_> = missing
+                    .iter()
+                    .map(|pattern| format!("`{}`", pretty_print_pattern(pattern)))
+                    .collect();
+                (
+                    *e_idx,
+                    format!(
+                        "non-exhaustive patterns: {} not covered",
+                        missing.join(", ")
+                    ),
+                )
+            }
+            PatternError::Redundant(e_idx, index) => {
+                (*e_idx, format!("branch {} is unreachable", index + 1))
+            }
+        };
+        self.error(self.context.expr_span(e_idx).as_ref(), &message)
+    }
+
+    pub(crate) fn unsolved(&self, deferred: &DeferredAssertion) -> Diagnostic {
+        let assertion = deferred.assertion();
+        let message = format!(
+            "could not solve `{}`",
+            self.printer()
+                .print_assertion(&Zonk::new(self.context).zonk_assertion(assertion))
+        );
+        let span = deferred
+            .origin()
+            .and_then(|e_idx| self.context.expr_span(e_idx));
+        self.error(span.as_ref(), &message)
+    }
+
+    pub(crate) fn infer_error(&self, error: &InferError) -> Diagnostic {
+        self.error(
+            self.context.expr_span(error.origin).as_ref(),
+            &error.message,
+        )
+    }
+
+    pub(crate) fn error(&self, span: Option<&Span>, message: &str) -> Diagnostic {
+        let rendered = match span {
+            None => format!("error: {}", message),
+            Some(span) => match self.sources.get(&span.file) {
+                Some(source) => {
+                    let (line, column) = position(source, span.start);
+                    format!("{}:{}:{}: error: {}", span.file, line, column, message)
+                }
+                None => format!("{}: error: {}", span.file, message),
+            },
+        };
+        Diagnostic {
+            span: span.cloned(),
+            message: message.into(),
+            rendered,
+        }
+    }
+
+    fn print_ty(&self, printer: &mut TypePrinter, t_idx: TypeIdx) -> String {
+        printer.print_ty(Zonk::new(self.context).zonk(t_idx))
+    }
+
+    fn unification(&self, name: usize) -> TypeIdx {
+        self.context
+            .type_arena()
+            .allocate(Type::Unification { name })
+    }
+
+    fn printer(&self) -> TypePrinter<'a> {
+        TypePrinter::new(
+            self.context.type_arena(),
+            PrettyOptions {
+                naming: Naming::Letters,
+                ..PrettyOptions::default()
+            },
+        )
+    }
+}
+
+/// Lines and columns are counted from one, with columns in characters.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = match before.rfind('\n') {
+        Some(newline) => before[newline + 1..].chars().count() + 1,
+        None => before.chars().count() + 1,
+    };
+    (line, column)
+}
+
+fn partial_synonym(ty: &str, arity: usize, given: usize) -> String {
+    format!(
+        "the synonym in `{}` expects {} arguments but was given {}",
+        ty, arity, given
+    )
+}
@@ -0,0 +1,369 @@
+mod check;
+mod diagnostic;
+mod repl;
+
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+
+use crate::check::{check, Dumps};
+
+/// Type checks a source file, printing the type of each top-level binding.
+#[derive(Parser)]
+#[command(name = "iwc", args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(required = true)]
+    file: Option<PathBuf>,
+    /// Print every constraint in the order that it's solved.
+    #[arg(long)]
+    dump_constraints: bool,
+    /// Print the solutions to unification variables.
+    #[arg(long)]
+    dump_solutions: bool,
+    /// Print the evidence for each entailed assertion.
+    #[arg(long)]
+    dump_evidence: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Starts an interactive session for declaring and querying types.
+    Repl,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let file = match (args.command, args.file) {
+        (Some(Command::Repl), _) => {
+            return match repl::run() {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(error) => {
+                    eprintln!("error: {}", error);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        (None, Some(file)) => file,
+        (None, None) => unreachable!("the file is required without a subcommand"),
+    };
+
+    let source = match fs::read_to_string(&file) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("error: could not read {}: {}", file.display(), error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let dumps = Dumps {
+        constraints: args.dump_constraints,
+        solutions: args.dump_solutions,
+        evidence: args.dump_evidence,
+    };
+    let report = check(&file.to_string_lossy(), &source, &dumps);
+
+    print!("{}", report.output);
+    for diagnostic in &report.diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+
+    if report.diagnostics.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::{check, Dumps},
+        repl::Repl,
+    };
+
+    #[test]
+    fn checking_files() {
+        let source = "
+data Maybe a = Nothing | Just a
+
+class Eq a
+
+instance Eq Int
+
+instance Eq a => Eq (Maybe a)
+
+equals :: Eq a => a -> a -> Boolean
+equals x y = true
+
+notEquals :: Eq a => a -> a -> Boolean
+notEquals x y = equals x y
+
+fromMaybe d m = case m of { Nothing -> d; Just x -> x }
+
+same = equals (Just 1) (Just 2)
+";
+        let dumps = Dumps {
+            evidence: true,
+            ..Dumps::default()
+        };
+        let report = check("Main.iwc", source, &dumps);
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+        assert_eq!(
+            report.output,
+            "\
+-- evidence
+#3: given #1
+#13: dictionary [#19]
+#19: dictionary []
+equals :: forall a. Eq a => a -> a -> Boolean
+notEquals :: forall a. Eq a => a -> a -> Boolean
+fromMaybe :: forall a. a -> Maybe a -> a
+same :: Boolean
+"
+        );
+
+        let source = "
+data Maybe a = Nothing | Just a
+
+wrong :: Int
+wrong = \"text\"
+
+partial m = case m of { Just x -> x }
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert_eq!(report.output, "");
+        assert_eq!(
+            report.diagnostics,
+            [
+                "Main.iwc:5:9: error: cannot unify `Int` with `String`",
+                "Main.iwc:7:13: error: non-exhaustive patterns: `Nothing` not covered",
+            ]
+        );
+
+        let report = check("Main.iwc", "\nmain = show 1\n", &Dumps::default());
+        assert_eq!(
+            report.diagnostics,
+            ["Main.iwc:2:8: error: Could not find value: \"show\""]
+        );
+
+        let source = "
+class Eq a
+
+equals :: Eq a => a -> a -> Boolean
+equals x y = true
+
+same = equals 1 2
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert_eq!(
+            report.diagnostics,
+            ["Main.iwc:7:8: error: could not solve `Eq Int`"]
+        );
+
+        let source = "
+data Maybe a = Nothing | Just a
+
+f :: Int -> Maybe Int
+f x = Nothing
+
+g :: Int -> Maybe
+g x = Nothing
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert_eq!(
+            report.diagnostics,
+            ["Main.iwc:7:1: error: cannot unify the kind `Type -> Type` with `Type` in `Maybe`"]
+        );
+
+        // Functions can be applied to fewer arguments than they take, and
+        // take more than their signature lists up front.
+        let source = "
+add x y = x
+
+inc = add 1
+
+f :: Int -> Int -> Int
+f x = \\y -> y
+
+g :: Int -> (Int -> Int)
+g x y = y
+
+h = f 1
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+        assert_eq!(
+            report.output,
+            "\
+add :: forall a b. a -> b -> a
+inc :: forall a. a -> Int
+f :: Int -> Int -> Int
+g :: Int -> (Int -> Int)
+h :: Int -> Int
+"
+        );
+
+        // The same error in two places is reported in both.
+        let source = "
+a :: Int
+a = \"text\"
+
+b :: Int
+b = \"text\"
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert_eq!(
+            report.diagnostics,
+            [
+                "Main.iwc:3:5: error: cannot unify `Int` with `String`",
+                "Main.iwc:6:5: error: cannot unify `Int` with `String`",
+            ]
+        );
+
+        // Bindings that fail don't keep the rest from being checked.
+        let source = "
+main = (1 :: String) nope
+
+other :: Int
+other = missing
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert_eq!(
+            report.diagnostics,
+            [
+                "Main.iwc:2:9: error: cannot unify `String` with `Int`",
+                "Main.iwc:2:22: error: Could not find value: \"nope\"",
+                "Main.iwc:5:9: error: Could not find value: \"missing\"",
+            ]
+        );
+
+        let source = "
+f :: Foo a => a -> a
+f x = x
+
+g = f 1
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert_eq!(
+            report.diagnostics,
+            ["Main.iwc:2:1: error: unknown class `Foo`"]
+        );
+
+        let source = "
+class Eq a
+
+instance Bar Int
+
+instance Eq Int Int
+
+f :: Eq a a => a -> a
+f x = x
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert_eq!(
+            report.diagnostics,
+            [
+                "Main.iwc:4:1: error: unknown class `Bar`",
+                "Main.iwc:6:1: error: the class `Eq` expects 1 arguments but was given 2",
+                "Main.iwc:8:1: error: the class `Eq` expects 1 arguments but was given 2",
+            ]
+        );
+
+        // Instance variables match the wanted side's type variables whatever
+        // they're named.
+        let source = "
+data Box a = Box a
+
+class Eq a
+
+instance Eq a => Eq (Box a)
+
+g :: Eq a => a -> Boolean
+g x = true
+
+f :: Eq b => Box b -> Boolean
+f x = g x
+";
+        let report = check("Main.iwc", source, &Dumps::default());
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+
+        // Constraints pile up until a binding is generalized, however many
+        // there are in between.
+        let fields: Vec<_> = (0..300)
+            .map(|index| format!("a{}: f (f 1)", index))
+            .collect();
+        let source = format!("f x = x\ng = {{ {} }}\n", fields.join(", "));
+        let report = check("Main.iwc", &source, &Dumps::default());
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+    }
+
+    #[test]
+    fn repl_session() {
+        let mut repl = Repl::new();
+        let mut input = |line: &str| repl.input(line);
+
+        assert_eq!(input("class Append xs ys zs | xs ys -> zs"), "");
+        assert_eq!(input("instance Append Nil ys ys"), "");
+        assert_eq!(
+            input("instance Append xs ys zs => Append (Cons x xs) ys (Cons x zs)"),
+            ""
+        );
+        assert_eq!(
+            input(":instances Append"),
+            "\
+instance Append Nil ys ys
+instance Append xs ys zs => Append (Cons x xs) ys (Cons x zs)
+"
+        );
+        assert_eq!(
+            input(":solve Append (Cons One Nil) (Cons Zero Nil) zs"),
+            "\
+Append (Cons One Nil) (Cons Zero Nil) (Cons One (Cons Zero Nil))
+  Append Nil (Cons Zero Nil) (Cons Zero Nil)
+zs := Cons One (Cons Zero Nil)
+"
+        );
+
+        assert_eq!(input("data Maybe a = Nothing | Just a"), "");
+        assert_eq!(input(":kind Maybe"), "Maybe :: Type -> Type\n");
+        assert_eq!(input("wrap x = Just x"), "wrap :: forall a. a -> Maybe a\n");
+        assert_eq!(
+            input(":type wrap (wrap 1)"),
+            "wrap (wrap 1) :: Maybe (Maybe Int)\n"
+        );
+
+        assert_eq!(
+            input("bad = wrap 1 2"),
+            "<repl:9>:1:7: error: cannot unify `Maybe Int` with `Int -> a`\n"
+        );
+        assert_eq!(
+            input(":type bad"),
+            "<repl:10>:1:1: error: Could not find value: \"bad\"\n"
+        );
+
+        // A failed definition leaves the earlier one in place.
+        assert_eq!(input("x = 1"), "x :: Int\n");
+        assert_eq!(
+            input("x = \\y -> y.a y.a.b"),
+            "<repl:12>:1:15: error: cannot unify `{ b :: a | b }` with `a -> c`\n"
+        );
+        assert_eq!(input(":type x"), "x :: Int\n");
+
+        assert_eq!(input(":kind m Int"), "m Int :: k0\n");
+        assert_eq!(
+            input(":kind a a"),
+            "<repl:15>:1:1: error: the kind of `a a` would be infinite, as in `k0 -> k1`\n"
+        );
+
+        // The constraints of a failed input don't carry over to the next.
+        assert_eq!(
+            input("y = (1 :: String) nope"),
+            "<repl:16>:1:6: error: cannot unify `String` with `Int`\n\
+             <repl:16>:1:19: error: Could not find value: \"nope\"\n"
+        );
+        assert_eq!(input("z = 1"), "z :: Int\n");
+    }
+}
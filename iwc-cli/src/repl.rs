@@ -0,0 +1,440 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+};
+
+use im::vector;
+use iwc_core_ast::{
+    decl::{Module, ValueDecl},
+    kind::KindPrinter,
+    span::Span,
+    ty::{
+        pretty::{Naming, PrettyOptions, TypePrinter},
+        Assertion, TypeIdx,
+    },
+};
+use iwc_core_constraint::Constraint;
+use iwc_core_elaborate::{
+    context::Context, declare::Declare, entail::Evidence, infer::Infer, instantiate::Instantiate,
+    kind_check::KindCheck, solve::Solve, zonk::Zonk,
+};
+use iwc_core_error::{KindError, ParseError};
+use iwc_parser::{parse_assertion, parse_expr, parse_module, parse_open_type, Arenas};
+use smol_str::SmolStr;
+
+use crate::diagnostic::{sorted, Diagnostic, Diagnostics, Sources};
+
+const HELP: &str = "\
+Declarations are entered one per line, and commands are:
+  :type <expr>          infers the type of an expression
+  :kind <type>          infers the kind of a type
+  :instances <Class>    lists the instances of a class
+  :solve <assertion>    solves an assertion, showing its evidence and solutions
+  :help                 shows this message
+  :quit                 exits
+";
+
+/// The name that the expression given to `:type` is bound to, which can't
+/// be written as a variable.
+const INPUT: &str = "<input>";
+
+pub(crate) fn run() -> io::Result<()> {
+    let mut repl = Repl::new();
+    let mut stdout = io::stdout();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            writeln!(stdout)?;
+            return Ok(());
+        };
+        if matches!(line.trim(), ":quit" | ":q") {
+            return Ok(());
+        }
+        write!(stdout, "{}", repl.input(&line))?;
+    }
+}
+
+/// A session that keeps a single context across inputs, where each input
+/// is a source file of its own for the sake of diagnostics.
+pub(crate) struct Repl {
+    context: Context,
+    sources: Sources,
+    /// The indices of deferred assertions that have already been reported.
+    reported: HashSet<usize>,
+}
+
+/// The number of errors in the context before an input is processed, such
+/// that only the errors caused by the input are reported.
+struct Marks {
+    kind: usize,
+    infer: usize,
+    pattern: usize,
+    unification: usize,
+}
+
+impl Repl {
+    pub(crate) fn new() -> Self {
+        Self {
+            context: Context::default(),
+            sources: Sources::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Processes a line of input, returning what's printed in response.
+    pub(crate) fn input(&mut self, line: &str) -> String {
+        let Some(command) = line.trim().strip_prefix(':') else {
+            if line.trim().is_empty() {
+                return String::new();
+            }
+            return self.declare(line);
+        };
+        let (command, argument) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let argument = argument.trim();
+        match command {
+            "type" | "t" => self.ty(argument),
+            "kind" | "k" => self.kind(argument),
+            "instances" | "i" => self.instances(argument),
+            "solve" | "s" => self.solve(argument),
+            "help" | "h" => HELP.into(),
+            _ => format!("error: unknown command `:{}`, see `:help`\n", command),
+        }
+    }
+
+    fn declare(&mut self, source: &str) -> String {
+        let file = self.source(source);
+        let (expr_arena, type_arena, expr_spans, type_spans) = self.context.arenas_mut();
+        let (module, errors) = parse_module(
+            &file,
+            source,
+            Arenas {
+                expr_arena,
+                type_arena,
+                expr_spans,
+                type_spans,
+            },
+        );
+        if !errors.is_empty() {
+            return self.parse_errors(&errors);
+        }
+
+        if let Err(errors) = self.infer(&module) {
+            return errors;
+        }
+
+        let mut output = String::new();
+        for value in &module.values {
+            if let Some(t_idx) = self.context.environment().value(&value.name) {
+                writeln!(output, "{} :: {}", value.name, self.print_ty(t_idx)).unwrap();
+            }
+        }
+        output
+    }
+
+    fn ty(&mut self, source: &str) -> String {
+        let file = self.source(source);
+        let (expr_arena, type_arena, expr_spans, type_spans) = self.context.arenas_mut();
+        let value = match parse_expr(
+            &file,
+            source,
+            Arenas {
+                expr_arena,
+                type_arena,
+                expr_spans,
+                type_spans,
+            },
+        ) {
+            Ok(value) => value,
+            Err(errors) => return self.parse_errors(&errors),
+        };
+
+        let module = Module {
+            values: vector![ValueDecl {
+                name: INPUT.into(),
+                signature: None,
+                signature_span: None,
+                value,
+            }],
+            ..Module::default()
+        };
+        if let Err(errors) = self.infer(&module) {
+            return errors;
+        }
+
+        match self.context.environment_mut().remove_value(INPUT) {
+            Some(t_idx) => format!("{} :: {}\n", source, self.print_ty(t_idx)),
+            None => String::new(),
+        }
+    }
+
+    fn kind(&mut self, source: &str) -> String {
+        let file = self.source(source);
+        let (expr_arena, type_arena, expr_spans, type_spans) = self.context.arenas_mut();
+        let t_idx = match parse_open_type(
+            &file,
+            source,
+            Arenas {
+                expr_arena,
+                type_arena,
+                expr_spans,
+                type_spans,
+            },
+        ) {
+            Ok(t_idx) => t_idx,
+            Err(errors) => return self.parse_errors(&errors),
+        };
+
+        let span = Span {
+            file,
+            start: 0,
+            end: source.len(),
+        };
+        match KindCheck::new(&self.context)
+            .with_span(Some(span))
+            .infer(t_idx)
+        {
+            Ok(kind) => format!(
+                "{} :: {}\n",
+                source,
+                KindPrinter::default().print_kind(&kind)
+            ),
+            Err(error) => {
+                let diagnostics = Diagnostics::new(&self.sources, &self.context);
+                format!("{}\n", diagnostics.kind_error(&error))
+            }
+        }
+    }
+
+    fn instances(&mut self, name: &str) -> String {
+        let instances = self.context.environment().find_instances(name);
+        if instances.is_empty() {
+            return format!("no instances of `{}`\n", name);
+        }
+
+        let mut output = String::new();
+        for instance in instances {
+            let mut printer = self.printer(Naming::Letters);
+            let mut dependencies: Vec<_> = instance
+                .dependencies
+                .iter()
+                .map(|dependency| printer.print_assertion(dependency))
+                .collect();
+            let head = printer.print_assertion(&instance.assertion);
+            let context = match dependencies.len() {
+                0 => String::new(),
+                1 => format!("{} => ", dependencies.remove(0)),
+                _ => format!("({}) => ", dependencies.join(", ")),
+            };
+            writeln!(output, "instance {}{}", context, head).unwrap();
+        }
+        output
+    }
+
+    /// Shows the evidence for an assertion as a tree of the instances that
+    /// it depends on, followed by the unification variables that solving it
+    /// solved. Type variables in the assertion stand for the types that are
+    /// solved for, as in `Append Nil Nil zs`.
+    fn solve(&mut self, source: &str) -> String {
+        let file = self.source(source);
+        let (expr_arena, type_arena, expr_spans, type_spans) = self.context.arenas_mut();
+        let assertion = match parse_assertion(
+            &file,
+            source,
+            Arenas {
+                expr_arena,
+                type_arena,
+                expr_spans,
+                type_spans,
+            },
+        ) {
+            Ok(assertion) => assertion,
+            Err(errors) => return self.parse_errors(&errors),
+        };
+        if self
+            .context
+            .environment()
+            .find_instances(&assertion.name)
+            .is_empty()
+        {
+            return format!("no instances of `{}`\n", assertion.name);
+        }
+
+        let (assertion, variables) =
+            Instantiate::new(&self.context).instantiate_assertion(&assertion);
+
+        let marks = self.marks();
+
+        // The constraints are only recorded while solving, as they're
+        // needed for showing the evidence and nowhere else.
+        self.context.record_constraints();
+        let index = Solve::new(&mut self.context).solve_assertion(assertion);
+        let recorded = self.context.stop_recording();
+
+        let assertions: HashMap<_, _> = recorded
+            .iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::ClassEntail(index, assertion, _) => Some((*index, assertion)),
+                _ => None,
+            })
+            .collect();
+
+        let mut printer = self.printer(Naming::Numbered);
+        let mut output = String::new();
+        self.evidence(&mut printer, &assertions, index, 0, &mut output);
+
+        let mut zonk = Zonk::new(&self.context);
+        for (name, t_idx) in variables {
+            writeln!(output, "{} := {}", name, printer.print_ty(zonk.zonk(t_idx))).unwrap();
+        }
+
+        for error in self.errors(&marks, vec![]) {
+            writeln!(output, "{}", error).unwrap();
+        }
+        output
+    }
+
+    fn evidence(
+        &self,
+        printer: &mut TypePrinter,
+        assertions: &HashMap<usize, &Assertion>,
+        index: usize,
+        depth: usize,
+        output: &mut String,
+    ) {
+        let Some(assertion) = assertions.get(&index) else {
+            return;
+        };
+        let indent = "  ".repeat(depth);
+        let assertion = Zonk::new(&self.context).zonk_assertion(assertion);
+        let assertion = printer.print_assertion(&assertion);
+        match self.context.solutions().entailment_evidences().get(&index) {
+            Some(Evidence::Dictionary { dependencies }) => {
+                writeln!(output, "{}{}", indent, assertion).unwrap();
+                for dependency in dependencies {
+                    self.evidence(printer, assertions, *dependency, depth + 1, output);
+                }
+            }
+            Some(Evidence::Given { .. }) => {
+                writeln!(output, "{}{} (given)", indent, assertion).unwrap()
+            }
+            None => writeln!(output, "{}{} (unsolved)", indent, assertion).unwrap(),
+        }
+    }
+
+    /// Declares and infers a module, reporting the errors it causes, after
+    /// which the environment is restored to what it was before the module,
+    /// including the earlier bindings of the names that it redefines. The
+    /// constraints of the module are solved even if it fails, so that none
+    /// are left over for the next input.
+    fn infer(&mut self, module: &Module) -> Result<(), String> {
+        let marks = self.marks();
+        let environment = self.context.environment().clone();
+
+        let mut declare = Declare::new(&mut self.context);
+        let mut kind_errors = vec![];
+        for data in &module.data {
+            if let Err(error) = declare.declare_data(data) {
+                kind_errors.push(error);
+            }
+        }
+        for class in &module.classes {
+            declare.declare_class(class);
+        }
+        for instance in &module.instances {
+            if let Err(error) = declare.declare_instance(instance) {
+                kind_errors.push(error);
+            }
+        }
+
+        let mut infer = Infer::new(&mut self.context);
+        let result = infer.infer_module(module);
+        infer.to_solve().solve();
+        let mut errors = self.errors(&marks, kind_errors);
+        if let Err(error) = result {
+            let diagnostics = Diagnostics::new(&self.sources, &self.context);
+            errors.push(diagnostics.error(None, &error.to_string()));
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        *self.context.environment_mut() = environment;
+        Err(sorted(errors)
+            .iter()
+            .map(|error| format!("{}\n", error))
+            .collect())
+    }
+
+    fn errors(&mut self, marks: &Marks, kind_errors: Vec<KindError>) -> Vec<Diagnostic> {
+        let diagnostics = Diagnostics::new(&self.sources, &self.context);
+        let mut errors: Vec<_> = kind_errors
+            .iter()
+            .chain(&self.context.kind_errors()[marks.kind..])
+            .map(|error| diagnostics.kind_error(error))
+            .collect();
+        errors.extend(
+            self.context.infer_errors()[marks.infer..]
+                .iter()
+                .map(|error| diagnostics.infer_error(error)),
+        );
+        errors.extend(
+            self.context.pattern_errors()[marks.pattern..]
+                .iter()
+                .map(|error| diagnostics.pattern_error(error)),
+        );
+        errors.extend(
+            self.context.solutions().unification_errors()[marks.unification..]
+                .iter()
+                .map(|(error, origin)| diagnostics.unify_error(error, *origin)),
+        );
+        for deferred in self.context.solutions().entailment_deferred() {
+            if self.reported.insert(deferred.index()) {
+                errors.push(diagnostics.unsolved(deferred));
+            }
+        }
+        errors
+    }
+
+    fn marks(&self) -> Marks {
+        Marks {
+            kind: self.context.kind_errors().len(),
+            infer: self.context.infer_errors().len(),
+            pattern: self.context.pattern_errors().len(),
+            unification: self.context.solutions().unification_errors().len(),
+        }
+    }
+
+    fn parse_errors(&self, errors: &[ParseError]) -> String {
+        let diagnostics = Diagnostics::new(&self.sources, &self.context);
+        errors
+            .iter()
+            .map(|error| format!("{}\n", diagnostics.parse_error(error)))
+            .collect()
+    }
+
+    /// Keeps the source of an input under a name of its own.
+    fn source(&mut self, source: &str) -> SmolStr {
+        let file = SmolStr::from(format!("<repl:{}>", self.sources.len() + 1));
+        self.sources.insert(file.clone(), source.into());
+        file
+    }
+
+    fn print_ty(&self, t_idx: TypeIdx) -> String {
+        self.printer(Naming::Letters).print_ty(t_idx)
+    }
+
+    fn printer(&self, naming: Naming) -> TypePrinter<'_> {
+        TypePrinter::new(
+            self.context.type_arena(),
+            PrettyOptions {
+                naming,
+                ..PrettyOptions::default()
+            },
+        )
+    }
+}
@@ -0,0 +1,49 @@
+use im::Vector;
+use smol_str::SmolStr;
+
+use crate::{
+    expr::ExprIdx,
+    span::Span,
+    ty::{FunctionalDependency, Instance, TypeIdx, TypeVariableBinder},
+};
+
+/// A data type declaration, where the type parameters appear in the fields
+/// of each constructor as type variables of rank 0.
+#[derive(Debug, Clone)]
+pub struct DataDecl {
+    pub name: SmolStr,
+    /// Where the declaration was written, which is where kind errors in
+    /// its fields are reported.
+    pub span: Option<Span>,
+    pub type_params: Vector<TypeVariableBinder>,
+    pub constructors: Vector<(SmolStr, Vector<TypeIdx>)>,
+}
+
+/// A class declaration such as `class Append xs ys zs | xs ys -> zs`,
+/// where functional dependencies refer to parameters by their position.
+#[derive(Debug, Clone)]
+pub struct ClassDecl {
+    pub name: SmolStr,
+    pub parameters: Vector<SmolStr>,
+    pub functional_dependencies: Vector<FunctionalDependency>,
+}
+
+/// A top-level value declaration, which is checked against its signature
+/// when it has one, and inferred and generalized otherwise.
+#[derive(Debug, Clone)]
+pub struct ValueDecl {
+    pub name: SmolStr,
+    pub signature: Option<TypeIdx>,
+    /// Where the signature was written, which is where kind errors in it
+    /// are reported.
+    pub signature_span: Option<Span>,
+    pub value: ExprIdx,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Module {
+    pub data: Vector<DataDecl>,
+    pub classes: Vector<ClassDecl>,
+    pub instances: Vector<Instance>,
+    pub values: Vector<ValueDecl>,
+}
@@ -0,0 +1,119 @@
+/// A document in the style of Wadler's "A prettier printer", where groups
+/// are laid out on a single line wherever they fit within the width.
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Nil,
+    Text(String),
+    /// A space when laid out flat, or a newline otherwise.
+    Line,
+    /// Nothing when laid out flat, or a newline otherwise.
+    Break,
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn nest(indent: usize, doc: Doc) -> Doc {
+        Doc::Nest(indent, Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    pub fn join(docs: impl IntoIterator<Item = Doc>, separator: Doc) -> Doc {
+        let mut joined = vec![];
+        for doc in docs {
+            if !joined.is_empty() {
+                joined.push(separator.clone());
+            }
+            joined.push(doc);
+        }
+        Doc::Concat(joined)
+    }
+
+    pub fn render(&self, width: usize) -> String {
+        let mut output = String::new();
+        let mut column = 0;
+        let mut stack = vec![(0, Mode::Break, self)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Nil => (),
+                Doc::Text(text) => {
+                    output.push_str(text);
+                    column += text.chars().count();
+                }
+                Doc::Line | Doc::Break => match mode {
+                    Mode::Flat => {
+                        if let Doc::Line = doc {
+                            output.push(' ');
+                            column += 1;
+                        }
+                    }
+                    Mode::Break => {
+                        output.push('\n');
+                        output.extend(std::iter::repeat_n(' ', indent));
+                        column = indent;
+                    }
+                },
+                Doc::Nest(nested, doc) => stack.push((indent + nested, mode, doc)),
+                Doc::Group(doc) => {
+                    let mode = if mode == Mode::Flat || fits(remaining(width, column), doc, &stack)
+                    {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.push((indent, mode, doc));
+                }
+                Doc::Concat(docs) => {
+                    for doc in docs.iter().rev() {
+                        stack.push((indent, mode, doc));
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+fn remaining(width: usize, column: usize) -> isize {
+    isize::try_from(width.saturating_sub(column)).unwrap_or(isize::MAX)
+}
+
+/// Whether `doc` fits on what's `remaining` of the line when it's laid out
+/// flat, along with whatever follows it in `rest` up to the next newline.
+fn fits(mut remaining: isize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev().map(|(_, mode, doc)| (*mode, *doc));
+    let mut stack = vec![(Mode::Flat, doc)];
+    while let Some((mode, doc)) = stack.pop().or_else(|| rest.next()) {
+        if remaining < 0 {
+            return false;
+        }
+        match doc {
+            Doc::Nil => (),
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line | Doc::Break if mode == Mode::Break => return true,
+            Doc::Line => remaining -= 1,
+            Doc::Break => (),
+            Doc::Nest(_, doc) | Doc::Group(doc) => stack.push((mode, doc)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+        }
+    }
+    remaining >= 0
+}
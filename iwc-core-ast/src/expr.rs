@@ -5,10 +5,45 @@ use im::Vector;
 use iwc_arena::Idx;
 use smol_str::SmolStr;
 
+use crate::ty::TypeIdx;
+
 pub type ExprIdx = Idx<Expr>;
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Integer(i64),
+    Float(f64),
+    String(SmolStr),
+    Char(char),
+    Boolean(bool),
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Variable {
+        name: SmolStr,
+    },
+    Literal {
+        literal: Literal,
+    },
+    Constructor {
+        name: SmolStr,
+        arguments: Vector<Pattern>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseBranch {
+    pub pattern: Pattern,
+    pub body: ExprIdx,
+}
+
 #[derive(Debug, Clone)]
 pub enum Expr {
+    Literal {
+        literal: Literal,
+    },
     Constructor {
         name: SmolStr,
     },
@@ -23,4 +58,32 @@ pub enum Expr {
         arguments: Vector<SmolStr>,
         body: ExprIdx,
     },
+    Let {
+        name: SmolStr,
+        value: ExprIdx,
+        body: ExprIdx,
+    },
+    LetRec {
+        bindings: Vector<(SmolStr, ExprIdx)>,
+        body: ExprIdx,
+    },
+    Case {
+        scrutinee: ExprIdx,
+        branches: Vector<CaseBranch>,
+    },
+    Annotation {
+        expr: ExprIdx,
+        ty: TypeIdx,
+    },
+    Record {
+        fields: Vector<(SmolStr, ExprIdx)>,
+    },
+    Access {
+        expr: ExprIdx,
+        label: SmolStr,
+    },
+    Update {
+        expr: ExprIdx,
+        fields: Vector<(SmolStr, ExprIdx)>,
+    },
 }
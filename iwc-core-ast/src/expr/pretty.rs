@@ -1 +1,218 @@
+use std::fmt::Write;
 
+use im::Vector;
+use iwc_arena::{Arena, ArenaMap, InternedArena};
+use smol_str::SmolStr;
+
+use crate::{
+    expr::{CaseBranch, Expr, ExprIdx, Literal, Pattern},
+    ty::{pretty::pretty_print_ty, Type, TypeIdx},
+};
+
+// NOTE: Expressions are printed at one of three levels of precedence, and
+// are parenthesized whenever they appear where a higher one is expected.
+const OPEN: usize = 0;
+const APPLICATION: usize = 1;
+const ATOM: usize = 2;
+
+/// Type annotations are printed as `_`, as there's no type arena to print
+/// them from; see `pretty_print_expr_with_types`.
+pub fn pretty_print_expr(expr_arena: &Arena<Expr>, expr_idx: ExprIdx) -> String {
+    Printer {
+        expr_arena,
+        types: None,
+    }
+    .print(expr_idx, OPEN)
+}
+
+/// Prints an expression where every node with an entry in `types` is
+/// annotated with that type.
+pub fn pretty_print_expr_with_types(
+    expr_arena: &Arena<Expr>,
+    expr_idx: ExprIdx,
+    type_arena: &InternedArena<Type>,
+    types: &ArenaMap<ExprIdx, TypeIdx>,
+) -> String {
+    Printer {
+        expr_arena,
+        types: Some((type_arena, types)),
+    }
+    .print(expr_idx, OPEN)
+}
+
+pub fn pretty_print_pattern(pattern: &Pattern) -> String {
+    print_pattern(pattern, OPEN)
+}
+
+struct Printer<'a> {
+    expr_arena: &'a Arena<Expr>,
+    types: Option<(&'a InternedArena<Type>, &'a ArenaMap<ExprIdx, TypeIdx>)>,
+}
+
+impl<'a> Printer<'a> {
+    fn print(&self, expr_idx: ExprIdx, precedence: usize) -> String {
+        if let Some((type_arena, types)) = self.types {
+            if let Some(ty_idx) = types.get(expr_idx) {
+                return format!(
+                    "({} :: {})",
+                    self.print_expr(expr_idx, OPEN),
+                    pretty_print_ty(type_arena, *ty_idx)
+                );
+            }
+        }
+        self.print_expr(expr_idx, precedence)
+    }
+
+    fn print_expr(&self, expr_idx: ExprIdx, precedence: usize) -> String {
+        let (result, level) = match &self.expr_arena[expr_idx] {
+            Expr::Literal { literal } => {
+                let result = print_literal(literal);
+                let level = if result.starts_with('-') {
+                    APPLICATION
+                } else {
+                    ATOM
+                };
+                (result, level)
+            }
+            Expr::Constructor { name } | Expr::Variable { name } => (name.to_string(), ATOM),
+            Expr::Application {
+                function,
+                arguments,
+            } => {
+                let mut result = self.print(*function, APPLICATION);
+                for argument in arguments {
+                    write!(result, " {}", self.print(*argument, ATOM)).unwrap();
+                }
+                (result, APPLICATION)
+            }
+            Expr::Lambda { arguments, body } => {
+                let mut result = String::from("\\");
+                for argument in arguments {
+                    write!(result, "{} ", argument).unwrap();
+                }
+                write!(result, "-> {}", self.print(*body, OPEN)).unwrap();
+                (result, OPEN)
+            }
+            Expr::Let { name, value, body } => (
+                format!(
+                    "let {} = {} in {}",
+                    name,
+                    self.print(*value, OPEN),
+                    self.print(*body, OPEN)
+                ),
+                OPEN,
+            ),
+            Expr::LetRec { bindings, body } => {
+                let bindings: Vec<_> = bindings
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, self.print(*value, OPEN)))
+                    .collect();
+                (
+                    format!(
+                        "let rec {} in {}",
+                        bindings.join("; "),
+                        self.print(*body, OPEN)
+                    ),
+                    OPEN,
+                )
+            }
+            Expr::Case {
+                scrutinee,
+                branches,
+            } => {
+                let branches: Vec<_> = branches
+                    .iter()
+                    .map(|CaseBranch { pattern, body }| {
+                        format!(
+                            "{} -> {}",
+                            print_pattern(pattern, OPEN),
+                            self.print(*body, OPEN)
+                        )
+                    })
+                    .collect();
+                (
+                    format!(
+                        "case {} of {{ {} }}",
+                        self.print(*scrutinee, OPEN),
+                        branches.join("; ")
+                    ),
+                    OPEN,
+                )
+            }
+            Expr::Annotation { expr, ty } => {
+                let ty = match self.types {
+                    Some((type_arena, _)) => pretty_print_ty(type_arena, *ty),
+                    None => "_".into(),
+                };
+                (
+                    format!("{} :: {}", self.print(*expr, APPLICATION), ty),
+                    OPEN,
+                )
+            }
+            Expr::Record { fields } => (format!("{{ {} }}", self.print_fields(fields, ":")), ATOM),
+            Expr::Access { expr, label } => {
+                (format!("{}.{}", self.print(*expr, ATOM), label), ATOM)
+            }
+            Expr::Update { expr, fields } => (
+                format!(
+                    "{} {{ {} }}",
+                    self.print(*expr, ATOM),
+                    self.print_fields(fields, " =")
+                ),
+                APPLICATION,
+            ),
+        };
+        parenthesize(result, level, precedence)
+    }
+
+    fn print_fields(&self, fields: &Vector<(SmolStr, ExprIdx)>, separator: &str) -> String {
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|(label, field)| format!("{}{} {}", label, separator, self.print(*field, OPEN)))
+            .collect();
+        fields.join(", ")
+    }
+}
+
+fn print_pattern(pattern: &Pattern, precedence: usize) -> String {
+    match pattern {
+        Pattern::Wildcard => "_".into(),
+        Pattern::Variable { name } => name.to_string(),
+        Pattern::Literal { literal } => {
+            let result = print_literal(literal);
+            if result.starts_with('-') {
+                parenthesize(result, APPLICATION, precedence)
+            } else {
+                result
+            }
+        }
+        Pattern::Constructor { name, arguments } => {
+            if arguments.is_empty() {
+                return name.to_string();
+            }
+            let mut result = name.to_string();
+            for argument in arguments {
+                write!(result, " {}", print_pattern(argument, ATOM)).unwrap();
+            }
+            parenthesize(result, APPLICATION, precedence)
+        }
+    }
+}
+
+fn print_literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Integer(value) => format!("{}", value),
+        Literal::Float(value) => format!("{:?}", value),
+        Literal::String(value) => format!("{:?}", value),
+        Literal::Char(value) => format!("{:?}", value),
+        Literal::Boolean(value) => format!("{}", value),
+    }
+}
+
+fn parenthesize(result: String, level: usize, precedence: usize) -> String {
+    if level < precedence {
+        format!("({})", result)
+    } else {
+        result
+    }
+}
@@ -1,17 +1,23 @@
 use iwc_arena::Arena;
 
-use crate::expr::{Expr, ExprIdx};
+use crate::{
+    expr::{Expr, ExprIdx},
+    span::Spans,
+};
 
 pub trait Traversal: Sized {
     fn arena(&mut self) -> &mut Arena<Expr>;
 
+    fn spans(&self) -> &Spans<Expr>;
+
     fn traverse_expr(&mut self, expr_idx: ExprIdx) -> ExprIdx {
         default_traverse_expr(self, expr_idx)
     }
 }
 
 pub fn default_traverse_expr<T: Traversal>(traversal: &mut T, expr_idx: ExprIdx) -> ExprIdx {
-    match &traversal.arena()[expr_idx] {
+    let traversed_idx = match &traversal.arena()[expr_idx] {
+        Expr::Literal { .. } => expr_idx,
         Expr::Constructor { .. } => expr_idx,
         Expr::Variable { .. } => expr_idx,
         Expr::Application {
@@ -39,5 +45,81 @@ pub fn default_traverse_expr<T: Traversal>(traversal: &mut T, expr_idx: ExprIdx)
 
             traversal.arena().allocate(Expr::Lambda { arguments, body })
         }
-    }
+        Expr::Let { name, value, body } => {
+            let name = name.clone();
+            let value = *value;
+            let body = *body;
+
+            let value = traversal.traverse_expr(value);
+            let body = traversal.traverse_expr(body);
+
+            traversal.arena().allocate(Expr::Let { name, value, body })
+        }
+        Expr::LetRec { bindings, body } => {
+            let mut bindings = bindings.clone();
+            let body = *body;
+
+            for (_, value) in bindings.iter_mut() {
+                *value = traversal.traverse_expr(*value);
+            }
+            let body = traversal.traverse_expr(body);
+
+            traversal.arena().allocate(Expr::LetRec { bindings, body })
+        }
+        Expr::Case {
+            scrutinee,
+            branches,
+        } => {
+            let scrutinee = *scrutinee;
+            let mut branches = branches.clone();
+
+            let scrutinee = traversal.traverse_expr(scrutinee);
+            for branch in branches.iter_mut() {
+                branch.body = traversal.traverse_expr(branch.body);
+            }
+
+            traversal.arena().allocate(Expr::Case {
+                scrutinee,
+                branches,
+            })
+        }
+        Expr::Annotation { expr, ty } => {
+            let expr = *expr;
+            let ty = *ty;
+
+            let expr = traversal.traverse_expr(expr);
+
+            traversal.arena().allocate(Expr::Annotation { expr, ty })
+        }
+        Expr::Record { fields } => {
+            let mut fields = fields.clone();
+
+            for (_, field) in fields.iter_mut() {
+                *field = traversal.traverse_expr(*field);
+            }
+
+            traversal.arena().allocate(Expr::Record { fields })
+        }
+        Expr::Access { expr, label } => {
+            let expr = *expr;
+            let label = label.clone();
+
+            let expr = traversal.traverse_expr(expr);
+
+            traversal.arena().allocate(Expr::Access { expr, label })
+        }
+        Expr::Update { expr, fields } => {
+            let expr = *expr;
+            let mut fields = fields.clone();
+
+            let expr = traversal.traverse_expr(expr);
+            for (_, field) in fields.iter_mut() {
+                *field = traversal.traverse_expr(*field);
+            }
+
+            traversal.arena().allocate(Expr::Update { expr, fields })
+        }
+    };
+    traversal.spans().inherit(expr_idx, traversed_idx);
+    traversed_idx
 }
@@ -0,0 +1,53 @@
+use std::{collections::HashMap, fmt::Write};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Type,
+    Row(Box<Kind>),
+    Arrow(Box<Kind>, Box<Kind>),
+    Variable(usize),
+}
+
+impl Kind {
+    pub fn arrow(argument: Kind, result: Kind) -> Kind {
+        Kind::Arrow(Box::new(argument), Box::new(result))
+    }
+}
+
+pub fn pretty_print_kind(kind: &Kind) -> String {
+    print_kind(kind, &mut |name| format!("?k{}", name))
+}
+
+/// Prints kinds for users, where kind variables are named `k0`, `k1`, and
+/// so on, in order of appearance across all the kinds printed with it.
+#[derive(Debug, Default)]
+pub struct KindPrinter {
+    variables: HashMap<usize, usize>,
+}
+
+impl KindPrinter {
+    pub fn print_kind(&mut self, kind: &Kind) -> String {
+        print_kind(kind, &mut |name| {
+            let fresh = self.variables.len();
+            format!("k{}", self.variables.entry(name).or_insert(fresh))
+        })
+    }
+}
+
+fn print_kind(kind: &Kind, variable: &mut impl FnMut(usize) -> String) -> String {
+    match kind {
+        Kind::Type => "Type".into(),
+        Kind::Row(kind) => format!("(Row {})", print_kind(kind, variable)),
+        Kind::Arrow(argument, result) => {
+            let mut accumulator = String::new();
+            match argument.as_ref() {
+                Kind::Arrow(..) => write!(accumulator, "({})", print_kind(argument, variable)),
+                _ => write!(accumulator, "{}", print_kind(argument, variable)),
+            }
+            .unwrap();
+            write!(accumulator, " -> {}", print_kind(result, variable)).unwrap();
+            accumulator
+        }
+        Kind::Variable(name) => variable(*name),
+    }
+}
@@ -1,2 +1,206 @@
+pub mod decl;
+pub mod doc;
 pub mod expr;
+pub mod kind;
+pub mod span;
 pub mod ty;
+
+#[cfg(test)]
+mod tests {
+    use im::vector;
+    use iwc_arena::{Arena, ArenaMap, InternedArena};
+
+    use crate::{
+        doc::Doc,
+        expr::{
+            pretty::{pretty_print_expr, pretty_print_expr_with_types},
+            CaseBranch, Expr, Literal, Pattern,
+        },
+        kind::Kind,
+        ty::{
+            pretty::{pretty_print_ty, Naming, PrettyOptions, TypePrinter},
+            Assertion, Type, TypeVariableBinder,
+        },
+    };
+
+    #[test]
+    fn expression_printing() {
+        let mut expr_arena = Arena::new();
+        let type_arena = InternedArena::new();
+
+        let arena = &mut expr_arena;
+        let f = arena.allocate(Expr::Variable { name: "f".into() });
+        let x = arena.allocate(Expr::Variable { name: "x".into() });
+        let just = arena.allocate(Expr::Constructor {
+            name: "Just".into(),
+        });
+        let minus_one = arena.allocate(Expr::Literal {
+            literal: Literal::Integer(-1),
+        });
+        let just_minus_one = arena.allocate(Expr::Application {
+            function: just,
+            arguments: vector![minus_one],
+        });
+        let f_x = arena.allocate(Expr::Application {
+            function: f,
+            arguments: vector![x],
+        });
+        let apply = arena.allocate(Expr::Lambda {
+            arguments: vector!["f".into(), "x".into()],
+            body: f_x,
+        });
+        let identity = arena.allocate(Expr::Lambda {
+            arguments: vector!["x".into()],
+            body: x,
+        });
+        let expression = arena.allocate(Expr::Application {
+            function: apply,
+            arguments: vector![identity, just_minus_one],
+        });
+        let record = arena.allocate(Expr::Record {
+            fields: vector![("value".into(), expression)],
+        });
+        let access = arena.allocate(Expr::Access {
+            expr: record,
+            label: "value".into(),
+        });
+        let case = arena.allocate(Expr::Case {
+            scrutinee: access,
+            branches: vector![
+                CaseBranch {
+                    pattern: Pattern::Constructor {
+                        name: "Just".into(),
+                        arguments: vector![Pattern::Constructor {
+                            name: "Just".into(),
+                            arguments: vector![Pattern::Wildcard],
+                        }],
+                    },
+                    body: x,
+                },
+                CaseBranch {
+                    pattern: Pattern::Variable { name: "y".into() },
+                    body: identity,
+                },
+            ],
+        });
+
+        assert_eq!(
+            pretty_print_expr(&expr_arena, case),
+            "case { value: (\\f x -> f x) (\\x -> x) (Just (-1)) }.value of \
+             { Just (Just _) -> x; y -> \\x -> x }"
+        );
+
+        let int = type_arena.allocate(Type::Constructor { name: "Int".into() });
+        let annotation = expr_arena.allocate(Expr::Annotation { expr: x, ty: int });
+        assert_eq!(pretty_print_expr(&expr_arena, annotation), "x :: _");
+
+        let mut types = ArenaMap::new();
+        types.insert(minus_one, int);
+        types.insert(x, int);
+        assert_eq!(
+            pretty_print_expr_with_types(&expr_arena, just_minus_one, &type_arena, &types),
+            "Just (-1 :: Int)"
+        );
+        assert_eq!(
+            pretty_print_expr_with_types(&expr_arena, f_x, &type_arena, &types),
+            "f (x :: Int)"
+        );
+    }
+
+    #[test]
+    fn doc_layout() {
+        let doc = Doc::concat([
+            Doc::group(Doc::concat([
+                Doc::text("aaaa"),
+                Doc::Line,
+                Doc::text("bbbb"),
+            ])),
+            Doc::text("cccc"),
+            Doc::Line,
+            Doc::text("dddd"),
+        ]);
+        // The group would fit on its own, but not with the text after it.
+        assert_eq!(doc.render(10), "aaaa\nbbbbcccc\ndddd");
+        assert_eq!(doc.render(13), "aaaa bbbbcccc\ndddd");
+    }
+
+    #[test]
+    fn type_printing() {
+        let arena = &InternedArena::new();
+        let a = arena.allocate(Type::Variable {
+            name: "a".into(),
+            rank: 0,
+        });
+        let maybe = arena.allocate(Type::Constructor {
+            name: "Maybe".into(),
+        });
+        let maybe_a = arena.allocate(Type::Application {
+            function: maybe,
+            arguments: vector![a],
+        });
+        let a_a = arena.allocate(Type::Function {
+            arguments: vector![a],
+            result: a,
+        });
+        let t = arena.allocate(Type::Unification { name: 0 });
+        let u = arena.allocate(Type::Unification { name: 1 });
+        let maybe_t = arena.allocate(Type::Application {
+            function: maybe,
+            arguments: vector![t],
+        });
+        let function = arena.allocate(Type::Function {
+            arguments: vector![a_a, maybe_a],
+            result: maybe_t,
+        });
+        let function = arena.allocate(Type::Function {
+            arguments: vector![u],
+            result: function,
+        });
+        let constrained = arena.allocate(Type::Constrained {
+            assertions: vector![Assertion {
+                name: "Eq".into(),
+                arguments: vector![maybe_a],
+            }],
+            ty: function,
+        });
+        let forall = arena.allocate(Type::Forall {
+            variables: vector![TypeVariableBinder {
+                name: "a".into(),
+                kind: Some(Kind::Type),
+            }],
+            rank: 0,
+            ty: constrained,
+        });
+
+        let mut printer = TypePrinter::new(arena, PrettyOptions::default());
+        assert_eq!(
+            printer.print_ty(forall),
+            "forall (a :: Type). Eq (Maybe a) => t0 -> ((a -> a) -> Maybe a -> Maybe t1)"
+        );
+        assert_eq!(printer.print_ty(t), "t1");
+
+        let mut printer = TypePrinter::new(
+            arena,
+            PrettyOptions {
+                width: 40,
+                unicode: true,
+                naming: Naming::Letters,
+            },
+        );
+        assert_eq!(
+            printer.print_ty(forall),
+            "∀ (a :: Type).\n  Eq (Maybe a) ⇒\n  b → ((a → a) → Maybe a → Maybe c)"
+        );
+
+        let tail = arena.allocate(Type::Row {
+            labels: vector![("y".into(), t)],
+            tail: Some(u),
+        });
+        let row = arena.allocate(Type::Row {
+            labels: vector![("x".into(), a)],
+            tail: Some(tail),
+        });
+        let record = arena.allocate(Type::Record { row });
+        assert_eq!(pretty_print_ty(arena, record), "{ x :: a, y :: t0 | t1 }");
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Mutex;
+
+use iwc_arena::{ArenaMap, Idx};
+use smol_str::SmolStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: SmolStr,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A side table of source spans for the nodes in an arena.
+///
+/// Spans are stored behind a lock, as types are allocated through shared
+/// references and their spans need to be recorded the same way.
+pub struct Spans<T> {
+    spans: Mutex<ArenaMap<Idx<T>, Span>>,
+}
+
+impl<T> Spans<T> {
+    pub fn new() -> Self {
+        Self {
+            spans: Mutex::new(ArenaMap::new()),
+        }
+    }
+
+    pub fn get(&self, index: Idx<T>) -> Option<Span> {
+        self.spans.lock().unwrap().get(index).cloned()
+    }
+
+    pub fn insert(&self, index: Idx<T>, span: Span) {
+        self.spans.lock().unwrap().insert(index, span);
+    }
+
+    /// Records the `span` for `index` unless it already has one, which is
+    /// the case for interned types that were first allocated elsewhere.
+    pub fn insert_if_missing(&self, index: Idx<T>, span: Span) {
+        let mut spans = self.spans.lock().unwrap();
+        if !spans.contains_key(index) {
+            spans.insert(index, span);
+        }
+    }
+
+    /// Carries the span of `from` over to `to`, such that nodes rebuilt by
+    /// traversals still point back to where they came from.
+    pub fn inherit(&self, from: Idx<T>, to: Idx<T>) {
+        if from != to {
+            if let Some(span) = self.get(from) {
+                self.insert_if_missing(to, span);
+            }
+        }
+    }
+}
+
+impl<T> Default for Spans<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
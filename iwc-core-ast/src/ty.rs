@@ -7,20 +7,23 @@ use im::Vector;
 use iwc_arena::Idx;
 use smol_str::SmolStr;
 
+use crate::{kind::Kind, span::Span};
+
 pub type TypeIdx = Idx<Type>;
 
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TypeVariableBinder {
     pub name: SmolStr,
+    pub kind: Option<Kind>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Assertion {
     pub name: SmolStr,
     pub arguments: Vector<TypeIdx>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Type {
     Constructor {
         name: SmolStr,
@@ -49,6 +52,13 @@ pub enum Type {
         assertions: Vector<Assertion>,
         ty: TypeIdx,
     },
+    Row {
+        labels: Vector<(SmolStr, TypeIdx)>,
+        tail: Option<TypeIdx>,
+    },
+    Record {
+        row: TypeIdx,
+    },
 }
 
 impl Type {
@@ -61,6 +71,9 @@ impl Type {
 pub struct Instance {
     pub assertion: Assertion,
     pub dependencies: Vector<Assertion>,
+    /// Where the instance was written, which is where it's reported when
+    /// it doesn't fit its class.
+    pub span: Option<Span>,
 }
 
 #[derive(Debug, Clone)]
@@ -71,5 +84,16 @@ pub struct FunctionalDependency {
 
 #[derive(Debug, Clone)]
 pub struct Class {
+    /// The number of parameters, which is how many arguments every instance
+    /// and assertion of the class gives.
+    pub arity: usize,
     pub functional_dependencies: Vector<FunctionalDependency>,
 }
+
+/// A type synonym such as `type Pair a = Tuple a a`, where the parameters
+/// appear in `ty` as type variables of rank 0.
+#[derive(Debug, Clone)]
+pub struct Synonym {
+    pub parameters: Vector<SmolStr>,
+    pub ty: TypeIdx,
+}
@@ -1,82 +1,342 @@
-use std::fmt::Write;
+use std::collections::{HashMap, HashSet};
 
-use iwc_arena::Arena;
+use iwc_arena::InternedArena;
+use smol_str::SmolStr;
 
-use crate::ty::{Type, TypeIdx, TypeVariableBinder};
+use crate::{
+    doc::Doc,
+    kind::{pretty_print_kind, Kind},
+    ty::{Type, TypeIdx, TypeVariableBinder},
+};
 
 use super::Assertion;
 
-pub fn pretty_print_ty(type_arena: &Arena<Type>, ty_idx: TypeIdx) -> String {
-    match &type_arena[ty_idx] {
-        Type::Constructor { name } => format!("{}", name),
-        Type::Variable { name, rank } => format!("{}_{}", name, rank),
-        Type::Unification { name } => format!("?{}", name),
-        Type::Function { arguments, result } => {
-            let mut accumulator = String::new();
-            for argument in arguments {
-                write!(
-                    accumulator,
-                    "{} -> ",
-                    pretty_print_ty(type_arena, *argument)
-                )
-                .unwrap();
-            }
-            write!(accumulator, "{}", pretty_print_ty(type_arena, *result)).unwrap();
-            accumulator
+/// Prints a type on a single line with a printer of its own, such that
+/// unification variables are numbered from `t0` within each type.
+pub fn pretty_print_ty(type_arena: &InternedArena<Type>, ty_idx: TypeIdx) -> String {
+    TypePrinter::new(type_arena, PrettyOptions::flat()).print_ty(ty_idx)
+}
+
+pub fn pretty_print_assertion(type_arena: &InternedArena<Type>, assertion: &Assertion) -> String {
+    TypePrinter::new(type_arena, PrettyOptions::flat()).print_assertion(assertion)
+}
+
+/// How unification variables are named when printed by `TypePrinter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Naming {
+    /// `t0`, `t1`, and so on, in order of appearance.
+    Numbered,
+    /// Fresh letters that aren't used by the type variables printed so far.
+    Letters,
+    /// `?0`, `?1`, and so on, as they're named internally.
+    Internal,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrettyOptions {
+    pub width: usize,
+    pub unicode: bool,
+    pub naming: Naming,
+}
+
+impl PrettyOptions {
+    /// Options where nothing is ever broken across lines.
+    pub fn flat() -> Self {
+        Self {
+            width: usize::MAX,
+            ..Self::default()
         }
-        Type::Application {
-            function,
-            arguments,
-        } => {
-            let mut result = String::new();
-            write!(result, "({}", pretty_print_ty(type_arena, *function)).unwrap();
-            for argument in arguments {
-                write!(result, " {}", pretty_print_ty(type_arena, *argument)).unwrap();
-            }
-            write!(result, ")").unwrap();
-            result
+    }
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            unicode: false,
+            naming: Naming::Numbered,
         }
-        Type::Forall {
-            variables,
-            rank,
-            ty,
-        } => {
-            let mut result = String::new();
-            write!(result, "(forall_{} ", rank).unwrap();
-            for TypeVariableBinder { name } in variables {
-                write!(result, "{}", name).unwrap();
-            }
-            write!(result, ". {})", pretty_print_ty(type_arena, *ty)).unwrap();
-            result
+    }
+}
+
+// NOTE: Types are printed at one of three levels of precedence, and are
+// parenthesized whenever they appear where a higher one is expected.
+const OPEN: usize = 0;
+const APPLICATION: usize = 1;
+const ATOM: usize = 2;
+
+/// Prints types for messages, where unification variables are consistently
+/// renamed across every type printed with the same printer.
+pub struct TypePrinter<'a> {
+    type_arena: &'a InternedArena<Type>,
+    options: PrettyOptions,
+    unifications: HashMap<usize, SmolStr>,
+    used: HashSet<SmolStr>,
+}
+
+impl<'a> TypePrinter<'a> {
+    pub fn new(type_arena: &'a InternedArena<Type>, options: PrettyOptions) -> Self {
+        Self {
+            type_arena,
+            options,
+            unifications: HashMap::new(),
+            used: HashSet::new(),
         }
-        Type::Constrained { assertions, ty } => {
-            let mut result = String::new();
+    }
 
-            let mut assertions = assertions.iter().peekable();
+    pub fn print_ty(&mut self, ty_idx: TypeIdx) -> String {
+        self.collect_variables(ty_idx);
+        self.ty(ty_idx, OPEN).render(self.options.width)
+    }
 
-            write!(result, "(").unwrap();
-            while let Some(assertion) = assertions.next() {
-                write!(result, "{}", pretty_print_assertion(type_arena, assertion)).unwrap();
-                if assertions.peek().is_some() {
-                    write!(result, ", ").unwrap();
+    pub fn print_assertion(&mut self, assertion: &Assertion) -> String {
+        for argument in &assertion.arguments {
+            self.collect_variables(*argument);
+        }
+        self.assertion(assertion).render(self.options.width)
+    }
+
+    pub fn ty(&mut self, ty_idx: TypeIdx, precedence: usize) -> Doc {
+        let (doc, level) = match &self.type_arena[ty_idx] {
+            Type::Constructor { name } => (Doc::text(name.as_str()), ATOM),
+            Type::Variable { name, .. } => (Doc::text(name.as_str()), ATOM),
+            Type::Unification { name } => (Doc::text(self.unification(*name)), ATOM),
+            Type::Function { arguments, result } => {
+                let arrow = self.symbol(" →", " ->");
+                let mut docs = vec![];
+                for argument in arguments {
+                    docs.push(self.ty(*argument, APPLICATION));
+                    docs.push(Doc::text(arrow));
+                    docs.push(Doc::Line);
+                }
+                // Functions take all of their arguments at once, so one that
+                // returns another function keeps the two apart.
+                let level = match self.type_arena[*result] {
+                    Type::Function { .. } => APPLICATION,
+                    _ => OPEN,
+                };
+                docs.push(self.ty(*result, level));
+                (Doc::group(Doc::concat(docs)), OPEN)
+            }
+            Type::Application {
+                function,
+                arguments,
+            } => {
+                let mut docs = vec![self.ty(*function, APPLICATION)];
+                for argument in arguments {
+                    docs.push(Doc::Line);
+                    docs.push(self.ty(*argument, ATOM));
                 }
+                let mut docs = docs.into_iter();
+                let function = docs.next().unwrap();
+                (
+                    Doc::group(Doc::concat([function, Doc::nest(2, Doc::concat(docs))])),
+                    APPLICATION,
+                )
+            }
+            Type::Forall { variables, ty, .. } => {
+                let binders =
+                    variables
+                        .iter()
+                        .map(|TypeVariableBinder { name, kind }| match kind {
+                            Some(kind) => Doc::text(format!("({} :: {})", name, self.kind(kind))),
+                            None => Doc::text(name.as_str()),
+                        });
+                let binders = Doc::join(binders.collect::<Vec<_>>(), Doc::text(" "));
+                (
+                    Doc::group(Doc::concat([
+                        Doc::text(self.symbol("∀ ", "forall ")),
+                        binders,
+                        Doc::text("."),
+                        Doc::nest(2, Doc::concat([Doc::Line, self.ty(*ty, OPEN)])),
+                    ])),
+                    OPEN,
+                )
             }
-            write!(result, ") => ").unwrap();
+            Type::Constrained { assertions, ty } => {
+                let assertions: Vec<_> = assertions
+                    .iter()
+                    .map(|assertion| self.assertion(assertion))
+                    .collect();
+                let context = if assertions.len() == 1 {
+                    assertions.into_iter().next().unwrap()
+                } else {
+                    Doc::concat([
+                        Doc::text("("),
+                        Doc::join(assertions, Doc::text(", ")),
+                        Doc::text(")"),
+                    ])
+                };
+                (
+                    Doc::group(Doc::concat([
+                        context,
+                        Doc::text(self.symbol(" ⇒", " =>")),
+                        Doc::Line,
+                        self.ty(*ty, OPEN),
+                    ])),
+                    OPEN,
+                )
+            }
+            Type::Row { .. } => (self.row(ty_idx, "(", ")"), ATOM),
+            Type::Record { row } => match &self.type_arena[*row] {
+                Type::Row { .. } => (self.row(*row, "{", "}"), ATOM),
+                _ => (
+                    Doc::group(Doc::concat([
+                        Doc::text("Record"),
+                        Doc::nest(2, Doc::concat([Doc::Line, self.ty(*row, ATOM)])),
+                    ])),
+                    APPLICATION,
+                ),
+            },
+        };
+        if level < precedence {
+            Doc::concat([Doc::text("("), doc, Doc::text(")")])
+        } else {
+            doc
+        }
+    }
 
-            write!(result, "{}", pretty_print_ty(type_arena, *ty)).unwrap();
+    pub fn assertion(&mut self, assertion: &Assertion) -> Doc {
+        let mut docs = vec![Doc::text(assertion.name.as_str())];
+        for argument in &assertion.arguments {
+            docs.push(Doc::Line);
+            docs.push(self.ty(*argument, ATOM));
+        }
+        Doc::group(Doc::concat(docs))
+    }
 
-            result
+    fn row(&mut self, ty_idx: TypeIdx, open: &str, close: &str) -> Doc {
+        let Type::Row { labels, mut tail } = &self.type_arena[ty_idx] else {
+            return self.ty(ty_idx, OPEN);
+        };
+        // NOTE: A tail that's a row itself is printed as more labels.
+        let mut labels = labels.clone();
+        while let Some(Type::Row {
+            labels: more,
+            tail: next,
+        }) = tail.map(|tail| &self.type_arena[tail])
+        {
+            labels.extend(more.iter().cloned());
+            tail = *next;
+        }
+        if labels.is_empty() && tail.is_none() {
+            return Doc::text(format!("{}{}", open, close));
+        }
+        let labels: Vec<_> = labels
+            .iter()
+            .map(|(label, ty)| {
+                Doc::group(Doc::concat([
+                    Doc::text(format!("{} ::", label)),
+                    Doc::nest(2, Doc::concat([Doc::Line, self.ty(*ty, OPEN)])),
+                ]))
+            })
+            .collect();
+        let mut docs = vec![Doc::join(labels, Doc::concat([Doc::text(","), Doc::Line]))];
+        if let Some(tail) = tail {
+            docs.push(Doc::Line);
+            docs.push(Doc::text("| "));
+            docs.push(self.ty(tail, OPEN));
         }
+        Doc::group(Doc::concat([
+            Doc::text(open),
+            Doc::nest(2, Doc::concat([Doc::Line, Doc::concat(docs)])),
+            Doc::Line,
+            Doc::text(close),
+        ]))
     }
-}
 
-pub fn pretty_print_assertion(type_arena: &Arena<Type>, assertion: &Assertion) -> String {
-    let mut result = String::new();
+    fn kind(&self, kind: &Kind) -> String {
+        let kind = pretty_print_kind(kind);
+        if self.options.unicode {
+            kind.replace("->", "→")
+        } else {
+            kind
+        }
+    }
 
-    write!(result, "{}", assertion.name).unwrap();
-    for argument in assertion.arguments.iter() {
-        write!(result, " {}", pretty_print_ty(type_arena, *argument)).unwrap();
+    fn symbol(&self, unicode: &'static str, ascii: &'static str) -> &'static str {
+        if self.options.unicode {
+            unicode
+        } else {
+            ascii
+        }
     }
 
-    result
+    fn unification(&mut self, name: usize) -> SmolStr {
+        if let Some(renamed) = self.unifications.get(&name) {
+            return renamed.clone();
+        }
+        let renamed = match self.options.naming {
+            Naming::Internal => SmolStr::from(format!("?{}", name)),
+            Naming::Numbered => {
+                let renamed = SmolStr::from(format!("t{}", self.unifications.len()));
+                if self.used.contains(&renamed) {
+                    SmolStr::from(format!("?{}", renamed))
+                } else {
+                    renamed
+                }
+            }
+            Naming::Letters => (0..)
+                .flat_map(|suffix: usize| {
+                    ('a'..='z').map(move |letter| match suffix {
+                        0 => SmolStr::from(letter.to_string()),
+                        _ => SmolStr::from(format!("{}{}", letter, suffix)),
+                    })
+                })
+                .find(|renamed| !self.used.contains(renamed))
+                .unwrap(),
+        };
+        self.used.insert(renamed.clone());
+        self.unifications.insert(name, renamed.clone());
+        renamed
+    }
+
+    /// Records the names of type variables, such that unification variables
+    /// aren't renamed into them.
+    fn collect_variables(&mut self, ty_idx: TypeIdx) {
+        match &self.type_arena[ty_idx] {
+            Type::Constructor { .. } | Type::Unification { .. } => (),
+            Type::Variable { name, .. } => {
+                self.used.insert(name.clone());
+            }
+            Type::Function { arguments, result } => {
+                for argument in arguments {
+                    self.collect_variables(*argument);
+                }
+                self.collect_variables(*result);
+            }
+            Type::Application {
+                function,
+                arguments,
+            } => {
+                self.collect_variables(*function);
+                for argument in arguments {
+                    self.collect_variables(*argument);
+                }
+            }
+            Type::Forall { variables, ty, .. } => {
+                for TypeVariableBinder { name, .. } in variables {
+                    self.used.insert(name.clone());
+                }
+                self.collect_variables(*ty);
+            }
+            Type::Constrained { assertions, ty } => {
+                for assertion in assertions {
+                    for argument in &assertion.arguments {
+                        self.collect_variables(*argument);
+                    }
+                }
+                self.collect_variables(*ty);
+            }
+            Type::Row { labels, tail } => {
+                for (_, ty) in labels {
+                    self.collect_variables(*ty);
+                }
+                if let Some(tail) = tail {
+                    self.collect_variables(*tail);
+                }
+            }
+            Type::Record { row } => self.collect_variables(*row),
+        }
+    }
 }
@@ -1,9 +1,14 @@
-use iwc_arena::Arena;
+use iwc_arena::InternedArena;
 
-use crate::ty::{Assertion, Type, TypeIdx};
+use crate::{
+    span::Spans,
+    ty::{Assertion, Type, TypeIdx, TypeVariableBinder},
+};
 
 pub trait Traversal: Sized {
-    fn arena(&mut self) -> &mut Arena<Type>;
+    fn arena(&self) -> &InternedArena<Type>;
+
+    fn spans(&self) -> &Spans<Type>;
 
     fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
         default_traverse_ty(self, ty_idx)
@@ -12,10 +17,14 @@ pub trait Traversal: Sized {
     fn traverse_assertion(&mut self, assertion: &Assertion) -> Assertion {
         default_traverse_assertion(self, assertion)
     }
+
+    fn traverse_binder(&mut self, binder: &TypeVariableBinder) -> TypeVariableBinder {
+        binder.clone()
+    }
 }
 
 pub fn default_traverse_ty<T: Traversal>(traversal: &mut T, ty_idx: TypeIdx) -> TypeIdx {
-    match &traversal.arena()[ty_idx] {
+    let traversed_idx = match &traversal.arena()[ty_idx] {
         Type::Constructor { .. } => ty_idx,
         Type::Variable { .. } => ty_idx,
         Type::Unification { .. } => ty_idx,
@@ -54,11 +63,13 @@ pub fn default_traverse_ty<T: Traversal>(traversal: &mut T, ty_idx: TypeIdx) ->
             rank,
             ty,
         } => {
-            let variables = variables.clone();
+            let mut variables = variables.clone();
             let rank = *rank;
             let ty = *ty;
 
-            // TODO: kinded variable binders need to be traversed.
+            for variable in variables.iter_mut() {
+                *variable = traversal.traverse_binder(variable);
+            }
             let ty = traversal.traverse_ty(ty);
 
             traversal.arena().allocate(Type::Forall {
@@ -72,7 +83,7 @@ pub fn default_traverse_ty<T: Traversal>(traversal: &mut T, ty_idx: TypeIdx) ->
             let ty = *ty;
 
             for assertion in assertions.iter_mut() {
-                *assertion = traversal.traverse_assertion(&assertion)
+                *assertion = traversal.traverse_assertion(assertion)
             }
             let ty = traversal.traverse_ty(ty);
 
@@ -80,7 +91,27 @@ pub fn default_traverse_ty<T: Traversal>(traversal: &mut T, ty_idx: TypeIdx) ->
                 .arena()
                 .allocate(Type::Constrained { assertions, ty })
         }
-    }
+        Type::Row { labels, tail } => {
+            let mut labels = labels.clone();
+            let tail = *tail;
+
+            for (_, label) in labels.iter_mut() {
+                *label = traversal.traverse_ty(*label);
+            }
+            let tail = tail.map(|tail| traversal.traverse_ty(tail));
+
+            traversal.arena().allocate(Type::Row { labels, tail })
+        }
+        Type::Record { row } => {
+            let row = *row;
+
+            let row = traversal.traverse_ty(row);
+
+            traversal.arena().allocate(Type::Record { row })
+        }
+    };
+    traversal.spans().inherit(ty_idx, traversed_idx);
+    traversed_idx
 }
 
 pub fn default_traverse_assertion<T: Traversal>(
@@ -1,10 +1,15 @@
-use iwc_core_ast::ty::{Assertion, TypeIdx};
+use iwc_core_ast::{
+    expr::ExprIdx,
+    ty::{Assertion, TypeIdx},
+};
 use iwc_core_error::UnifyError;
 
-#[derive(Debug)]
+/// Each constraint carries the expression that it was emitted for, if any,
+/// which is where the errors that arise from solving it are reported.
+#[derive(Debug, Clone)]
 pub enum Constraint {
-    ClassEntail(usize, Assertion),
-    UnifyDeep(usize, usize),
-    UnifySolve(usize, TypeIdx),
-    UnifyError(UnifyError),
+    ClassEntail(usize, Assertion, Option<ExprIdx>),
+    UnifyDeep(usize, usize, Option<ExprIdx>),
+    UnifySolve(usize, TypeIdx, Option<ExprIdx>),
+    UnifyError(UnifyError, Option<ExprIdx>),
 }
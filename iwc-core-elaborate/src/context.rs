@@ -1,55 +1,247 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use concurrent_queue::ConcurrentQueue;
-use iwc_arena::Arena;
+use iwc_arena::{Arena, ArenaMap, InternedArena};
 use iwc_core_ast::{
-    expr::Expr,
-    ty::{Class, Instance, Type, TypeIdx},
+    expr::{Expr, ExprIdx},
+    kind::Kind,
+    span::{Span, Spans},
+    ty::{Assertion, Class, Instance, Synonym, Type, TypeIdx},
 };
 use iwc_core_constraint::Constraint;
+use iwc_core_error::{InferError, KindError, PatternError};
 use smol_str::SmolStr;
 
-#[derive(Default)]
+use crate::solve::Solutions;
+
+#[derive(Default, Clone)]
 pub struct Environment {
     pub(crate) constructors: HashMap<SmolStr, TypeIdx>,
+    pub(crate) constructor_types: HashMap<SmolStr, SmolStr>,
+    pub(crate) data: HashMap<SmolStr, Vec<SmolStr>>,
     pub(crate) values: HashMap<SmolStr, TypeIdx>,
     pub(crate) classes: HashMap<SmolStr, Class>,
     pub(crate) instances: HashMap<SmolStr, Vec<Instance>>,
+    pub(crate) kinds: HashMap<SmolStr, Kind>,
+    pub(crate) synonyms: HashMap<SmolStr, Synonym>,
+    pub(crate) primitives: Primitives,
 }
 
 impl Environment {
     pub fn find_instances(&self, name: &str) -> Vec<Instance> {
         self.instances.get(name).cloned().unwrap_or(vec![])
     }
+
+    pub fn set_primitives(&mut self, primitives: Primitives) {
+        self.primitives = primitives;
+    }
+
+    /// Checks that the class of `assertion` is declared and is given as many
+    /// arguments as it has parameters.
+    pub(crate) fn check_class(
+        &self,
+        assertion: &Assertion,
+        span: Option<Span>,
+    ) -> Result<(), KindError> {
+        let Some(class) = self.classes.get(&assertion.name) else {
+            return Err(KindError::UnknownClass(span, assertion.name.clone()));
+        };
+        if class.arity != assertion.arguments.len() {
+            return Err(KindError::ClassArity(
+                span,
+                assertion.name.clone(),
+                class.arity,
+                assertion.arguments.len(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Registers a type constructor of the given kind, which is how every
+    /// declared type is made known to kind checking. Registering a type
+    /// again forgets the data constructors that it had.
+    pub fn insert_type(&mut self, name: SmolStr, kind: Kind) {
+        self.kinds.insert(name.clone(), kind);
+        for constructor in self.data.insert(name.clone(), vec![]).unwrap_or_default() {
+            // NOTE: A later type may have taken the constructor's name since.
+            if self.constructor_types.get(&constructor) == Some(&name) {
+                self.constructors.remove(&constructor);
+                self.constructor_types.remove(&constructor);
+            }
+        }
+    }
+
+    /// Registers a data constructor of the type `data`, whose constructors
+    /// are kept in the order that they're registered in for exhaustiveness
+    /// checking.
+    pub fn insert_constructor(&mut self, name: SmolStr, data: SmolStr, t_idx: TypeIdx) {
+        self.constructors.insert(name.clone(), t_idx);
+        self.constructor_types.insert(name.clone(), data.clone());
+        self.data.entry(data).or_default().push(name);
+    }
+
+    /// Synonyms are declared through `Declare::declare_synonym`, which makes
+    /// sure that they don't refer to themselves.
+    pub(crate) fn insert_synonym(&mut self, name: SmolStr, synonym: Synonym) {
+        self.synonyms.insert(name, synonym);
+    }
+
+    pub fn value(&self, name: &str) -> Option<TypeIdx> {
+        self.values.get(name).copied()
+    }
+
+    pub fn remove_value(&mut self, name: &str) -> Option<TypeIdx> {
+        self.values.remove(name)
+    }
+}
+
+/// The type constructors assigned to literal expressions.
+#[derive(Debug, Clone)]
+pub struct Primitives {
+    pub integer: SmolStr,
+    pub float: SmolStr,
+    pub string: SmolStr,
+    pub char: SmolStr,
+    pub boolean: SmolStr,
+    /// When set, integer literals are overloaded through this class rather
+    /// than being assigned the `integer` type constructor.
+    pub integer_class: Option<SmolStr>,
+}
+
+impl Default for Primitives {
+    fn default() -> Self {
+        Self {
+            integer: "Int".into(),
+            float: "Number".into(),
+            string: "String".into(),
+            char: "Char".into(),
+            boolean: "Boolean".into(),
+            integer_class: None,
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct Volatile {
     pub(crate) expr_arena: Arena<Expr>,
-    pub(crate) type_arena: Arena<Type>,
+    pub(crate) type_arena: InternedArena<Type>,
+    pub(crate) expr_spans: Spans<Expr>,
+    pub(crate) type_spans: Spans<Type>,
 }
 
 pub struct Context {
     pub(crate) environment: Environment,
     pub(crate) volatile: Volatile,
-    pub(crate) fresh: usize,
+    pub(crate) fresh: AtomicUsize,
+    /// Unbounded, as constraints pile up until something solves them, which
+    /// may not happen before the whole module is inferred.
     pub(crate) constraints: ConcurrentQueue<Constraint>,
+    pub(crate) solutions: Solutions,
+    pub(crate) pattern_errors: Vec<PatternError>,
+    pub(crate) kind_errors: Vec<KindError>,
+    /// The errors of the bindings that couldn't be inferred or checked, which
+    /// the rest of the module is inferred past.
+    pub(crate) infer_errors: Vec<InferError>,
+    pub(crate) recorded: Option<Vec<Constraint>>,
+    /// The type of each expression as it was inferred or checked, before
+    /// it's zonked.
+    pub(crate) expr_types: ArenaMap<ExprIdx, TypeIdx>,
+    /// The assertions of the signatures being checked against, which are
+    /// assumed to hold for their skolems, along with their indices.
+    pub(crate) givens: Vec<(usize, Assertion)>,
 }
 
 impl Context {
-    pub fn fresh_index(&mut self) -> usize {
-        let index = self.fresh;
-        self.fresh += 1;
-        index
+    pub fn fresh_index(&self) -> usize {
+        self.fresh.fetch_add(1, Ordering::Relaxed)
     }
 
-    pub fn fresh_unification(&mut self) -> TypeIdx {
-        let name = self.fresh;
-        self.fresh += 1;
+    pub fn fresh_unification(&self) -> TypeIdx {
+        let name = self.fresh_index();
         self.volatile
             .type_arena
             .allocate(Type::Unification { name })
     }
+
+    pub fn expr_span(&self, e_idx: ExprIdx) -> Option<Span> {
+        self.volatile.expr_spans.get(e_idx)
+    }
+
+    pub fn type_span(&self, t_idx: TypeIdx) -> Option<Span> {
+        self.volatile.type_spans.get(t_idx)
+    }
+
+    pub fn pattern_errors(&self) -> &[PatternError] {
+        &self.pattern_errors
+    }
+
+    pub fn kind_errors(&self) -> &[KindError] {
+        &self.kind_errors
+    }
+
+    pub fn infer_errors(&self) -> &[InferError] {
+        &self.infer_errors
+    }
+
+    pub fn expr_type(&self, e_idx: ExprIdx) -> Option<TypeIdx> {
+        self.expr_types.get(e_idx).copied()
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.environment
+    }
+
+    pub fn solutions(&self) -> &Solutions {
+        &self.solutions
+    }
+
+    pub fn expr_arena(&self) -> &Arena<Expr> {
+        &self.volatile.expr_arena
+    }
+
+    pub fn type_arena(&self) -> &InternedArena<Type> {
+        &self.volatile.type_arena
+    }
+
+    /// Borrows the arenas apart, such that expressions can be allocated
+    /// while types are being interned, as they are during parsing.
+    pub fn arenas_mut(
+        &mut self,
+    ) -> (
+        &mut Arena<Expr>,
+        &InternedArena<Type>,
+        &Spans<Expr>,
+        &Spans<Type>,
+    ) {
+        let volatile = &mut self.volatile;
+        (
+            &mut volatile.expr_arena,
+            &volatile.type_arena,
+            &volatile.expr_spans,
+            &volatile.type_spans,
+        )
+    }
+
+    /// Keeps a copy of every constraint from here on as it's solved.
+    pub fn record_constraints(&mut self) {
+        self.recorded.get_or_insert_with(Vec::new);
+    }
+
+    pub fn recorded_constraints(&self) -> &[Constraint] {
+        self.recorded.as_deref().unwrap_or_default()
+    }
+
+    /// Stops keeping copies of constraints, returning the ones kept so far.
+    pub fn stop_recording(&mut self) -> Vec<Constraint> {
+        self.recorded.take().unwrap_or_default()
+    }
 }
 
 impl Default for Context {
@@ -57,8 +249,15 @@ impl Default for Context {
         Self {
             environment: Environment::default(),
             volatile: Volatile::default(),
-            fresh: 0,
-            constraints: ConcurrentQueue::bounded(512),
+            fresh: AtomicUsize::new(0),
+            constraints: ConcurrentQueue::unbounded(),
+            solutions: Solutions::default(),
+            pattern_errors: Vec::new(),
+            kind_errors: Vec::new(),
+            infer_errors: Vec::new(),
+            recorded: None,
+            expr_types: ArenaMap::new(),
+            givens: Vec::new(),
         }
     }
 }
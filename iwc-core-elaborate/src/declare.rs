@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+
+use im::Vector;
+use iwc_arena::InternedArena;
+use iwc_core_ast::{
+    decl::{ClassDecl, DataDecl},
+    kind::Kind,
+    span::Spans,
+    ty::{
+        traversal::{default_traverse_ty, Traversal},
+        Class, Instance, Synonym, Type, TypeIdx, TypeVariableBinder,
+    },
+};
+use iwc_core_error::KindError;
+use smol_str::SmolStr;
+
+use crate::{context::Context, kind_check::KindCheck};
+
+/// Populates the environment from declarations.
+pub struct Declare<'context> {
+    context: &'context mut Context,
+}
+
+impl<'context> Declare<'context> {
+    pub fn new(context: &'context mut Context) -> Self {
+        Self { context }
+    }
+
+    /// Registers the type constructor with its inferred kind, and each of
+    /// the data constructors with a function type into the declared type.
+    pub fn declare_data(&mut self, data: &DataDecl) -> Result<(), KindError> {
+        let kinds = KindCheck::new(self.context)
+            .with_span(data.span.clone())
+            .infer_data(data)?;
+
+        let type_params: Vector<_> = data
+            .type_params
+            .iter()
+            .zip(&kinds)
+            .map(
+                |(TypeVariableBinder { name, .. }, kind)| TypeVariableBinder {
+                    name: name.clone(),
+                    kind: Some(kind.clone()),
+                },
+            )
+            .collect();
+
+        let kind = kinds
+            .into_iter()
+            .rev()
+            .fold(Kind::Type, |result, argument| Kind::arrow(argument, result));
+        self.context
+            .environment
+            .insert_type(data.name.clone(), kind);
+
+        let result = self.result(data);
+        for (name, fields) in &data.constructors {
+            let ty = if fields.is_empty() {
+                result
+            } else {
+                self.allocate(Type::Function {
+                    arguments: fields.clone(),
+                    result,
+                })
+            };
+            let ty = if type_params.is_empty() {
+                ty
+            } else {
+                self.allocate(Type::Forall {
+                    variables: type_params.clone(),
+                    rank: 0,
+                    ty,
+                })
+            };
+            self.context
+                .environment
+                .insert_constructor(name.clone(), data.name.clone(), ty);
+        }
+
+        Ok(())
+    }
+
+    /// Registers a type synonym, rejecting one that refers to itself either
+    /// directly or through other synonyms, as it would never stop expanding.
+    pub fn declare_synonym(&mut self, name: SmolStr, synonym: Synonym) -> Result<(), KindError> {
+        let mut visited = HashSet::new();
+        let mut pending = vec![synonym.ty];
+        while let Some(t_idx) = pending.pop() {
+            let mut mentions = Mentions {
+                context: self.context,
+                constructors: vec![],
+            };
+            mentions.traverse_ty(t_idx);
+            for constructor in mentions.constructors {
+                if constructor == name {
+                    return Err(KindError::CyclicSynonym(None, synonym.ty, name));
+                }
+                if let Some(synonym) = self.context.environment.synonyms.get(&constructor) {
+                    if visited.insert(constructor) {
+                        pending.push(synonym.ty);
+                    }
+                }
+            }
+        }
+        self.context.environment.insert_synonym(name, synonym);
+        Ok(())
+    }
+
+    pub fn declare_class(&mut self, class: &ClassDecl) {
+        self.context
+            .environment
+            .instances
+            .entry(class.name.clone())
+            .or_default();
+        self.context.environment.classes.insert(
+            class.name.clone(),
+            Class {
+                arity: class.parameters.len(),
+                functional_dependencies: class.functional_dependencies.clone(),
+            },
+        );
+    }
+
+    /// Instances are tried in the order that they're declared in, and are
+    /// only declared for classes that they fit.
+    pub fn declare_instance(&mut self, instance: &Instance) -> Result<(), KindError> {
+        self.context
+            .environment
+            .check_class(&instance.assertion, instance.span.clone())?;
+        self.context
+            .environment
+            .instances
+            .entry(instance.assertion.name.clone())
+            .or_default()
+            .push(instance.clone());
+        Ok(())
+    }
+
+    fn result(&self, data: &DataDecl) -> TypeIdx {
+        let constructor = self.allocate(Type::Constructor {
+            name: data.name.clone(),
+        });
+        if data.type_params.is_empty() {
+            return constructor;
+        }
+        let arguments = data
+            .type_params
+            .iter()
+            .map(|TypeVariableBinder { name, .. }| {
+                self.allocate(Type::Variable {
+                    name: name.clone(),
+                    rank: 0,
+                })
+            })
+            .collect();
+        self.allocate(Type::Application {
+            function: constructor,
+            arguments,
+        })
+    }
+
+    fn allocate(&self, ty: Type) -> TypeIdx {
+        self.context.volatile.type_arena.allocate(ty)
+    }
+}
+
+/// Collects the type constructors that a type mentions.
+struct Mentions<'context> {
+    context: &'context Context,
+    constructors: Vec<SmolStr>,
+}
+
+impl<'context> Traversal for Mentions<'context> {
+    fn arena(&self) -> &InternedArena<Type> {
+        &self.context.volatile.type_arena
+    }
+
+    fn spans(&self) -> &Spans<Type> {
+        &self.context.volatile.type_spans
+    }
+
+    fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
+        if let Type::Constructor { name } = &self.context.volatile.type_arena[ty_idx] {
+            self.constructors.push(name.clone());
+            ty_idx
+        } else {
+            default_traverse_ty(self, ty_idx)
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+use iwc_arena::Arena;
+use iwc_core_ast::{
+    decl::ValueDecl,
+    expr::{CaseBranch, Expr, ExprIdx, Pattern},
+};
+use petgraph::{algo::tarjan_scc, graph::DiGraph};
+use smol_str::SmolStr;
+
+/// Splits value declarations into groups of mutually recursive ones, where
+/// each group only depends on the groups that come before it.
+pub(crate) fn binding_groups(arena: &Arena<Expr>, values: &[ValueDecl]) -> Vec<Vec<usize>> {
+    let mut graph = DiGraph::new();
+    let nodes: Vec<_> = (0..values.len())
+        .map(|index| graph.add_node(index))
+        .collect();
+    let names: HashMap<_, _> = values
+        .iter()
+        .enumerate()
+        .map(|(index, ValueDecl { name, .. })| (name.clone(), index))
+        .collect();
+
+    for (index, ValueDecl { value, .. }) in values.iter().enumerate() {
+        for name in free_variables(arena, *value) {
+            if let Some(dependency) = names.get(&name) {
+                graph.add_edge(nodes[index], nodes[*dependency], ());
+            }
+        }
+    }
+
+    // NOTE: tarjan_scc yields components in reverse topological order, so
+    // dependencies come before the groups that depend on them.
+    tarjan_scc(&graph)
+        .into_iter()
+        .map(|component| {
+            let mut group: Vec<_> = component.into_iter().map(|node| graph[node]).collect();
+            group.sort();
+            group
+        })
+        .collect()
+}
+
+pub(crate) fn free_variables(arena: &Arena<Expr>, e_idx: ExprIdx) -> HashSet<SmolStr> {
+    let mut free = HashSet::new();
+    collect(arena, e_idx, &mut vec![], &mut free);
+    free
+}
+
+fn collect(
+    arena: &Arena<Expr>,
+    e_idx: ExprIdx,
+    bound: &mut Vec<SmolStr>,
+    free: &mut HashSet<SmolStr>,
+) {
+    match &arena[e_idx] {
+        Expr::Literal { .. } | Expr::Constructor { .. } => (),
+        Expr::Variable { name } => {
+            if !bound.contains(name) {
+                free.insert(name.clone());
+            }
+        }
+        Expr::Application {
+            function,
+            arguments,
+        } => {
+            collect(arena, *function, bound, free);
+            for argument in arguments {
+                collect(arena, *argument, bound, free);
+            }
+        }
+        Expr::Lambda { arguments, body } => {
+            with_bound(bound, arguments.iter().cloned(), |bound| {
+                collect(arena, *body, bound, free)
+            });
+        }
+        Expr::Let { name, value, body } => {
+            collect(arena, *value, bound, free);
+            with_bound(bound, [name.clone()], |bound| {
+                collect(arena, *body, bound, free)
+            });
+        }
+        Expr::LetRec { bindings, body } => {
+            let names = bindings.iter().map(|(name, _)| name.clone());
+            with_bound(bound, names, |bound| {
+                for (_, value) in bindings {
+                    collect(arena, *value, bound, free);
+                }
+                collect(arena, *body, bound, free);
+            });
+        }
+        Expr::Case {
+            scrutinee,
+            branches,
+        } => {
+            collect(arena, *scrutinee, bound, free);
+            for CaseBranch { pattern, body } in branches {
+                let mut names = vec![];
+                pattern_variables(pattern, &mut names);
+                with_bound(bound, names, |bound| collect(arena, *body, bound, free));
+            }
+        }
+        Expr::Annotation { expr, .. } | Expr::Access { expr, .. } => {
+            collect(arena, *expr, bound, free);
+        }
+        Expr::Record { fields } => {
+            for (_, field) in fields {
+                collect(arena, *field, bound, free);
+            }
+        }
+        Expr::Update { expr, fields } => {
+            collect(arena, *expr, bound, free);
+            for (_, field) in fields {
+                collect(arena, *field, bound, free);
+            }
+        }
+    }
+}
+
+fn with_bound(
+    bound: &mut Vec<SmolStr>,
+    names: impl IntoIterator<Item = SmolStr>,
+    action: impl FnOnce(&mut Vec<SmolStr>),
+) {
+    let length = bound.len();
+    bound.extend(names);
+    action(bound);
+    bound.truncate(length);
+}
+
+fn pattern_variables(pattern: &Pattern, names: &mut Vec<SmolStr>) {
+    match pattern {
+        Pattern::Wildcard | Pattern::Literal { .. } => (),
+        Pattern::Variable { name } => names.push(name.clone()),
+        Pattern::Constructor { arguments, .. } => {
+            for argument in arguments {
+                pattern_variables(argument, names);
+            }
+        }
+    }
+}
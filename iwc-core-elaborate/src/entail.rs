@@ -3,19 +3,30 @@ use std::{
     iter::zip,
 };
 
-use iwc_core_ast::ty::{
-    traversal::{default_traverse_ty, Traversal},
-    Assertion, FunctionalDependency, Type, TypeIdx,
+use iwc_core_ast::{
+    span::Spans,
+    ty::{
+        traversal::{default_traverse_ty, Traversal},
+        Assertion, FunctionalDependency, Type, TypeIdx,
+    },
 };
 use smol_str::SmolStr;
 
+use crate::{expand::Expand, unify::applied, zonk::Zonk};
+
 pub struct Entail<'context> {
-    context: &'context mut crate::context::Context,
+    context: &'context crate::context::Context,
 }
 
 #[derive(Debug)]
 pub enum Evidence {
-    Dictionary { dependencies: Vec<usize> },
+    Dictionary {
+        dependencies: Vec<usize>,
+    },
+    /// The assertion is one of the givens, under the index of that given.
+    Given {
+        index: usize,
+    },
 }
 
 #[derive(Debug)]
@@ -35,7 +46,7 @@ pub enum EntailResult {
 }
 
 impl<'context> Entail<'context> {
-    pub fn new(context: &'context mut crate::context::Context) -> Self {
+    pub fn new(context: &'context crate::context::Context) -> Self {
         Self { context }
     }
 
@@ -45,6 +56,16 @@ impl<'context> Entail<'context> {
         t_idx: TypeIdx,
         u_idx: TypeIdx,
     ) -> bool {
+        if t_idx == u_idx && self.bind_identical(substitutions, t_idx) {
+            return true;
+        }
+        let expand = Expand::new(self.context);
+        let (Ok(t_expanded), Ok(u_expanded)) = (expand.expand(t_idx), expand.expand(u_idx)) else {
+            return false;
+        };
+        if (t_expanded, u_expanded) != (t_idx, u_idx) {
+            return self.match_argument(substitutions, t_expanded, u_expanded);
+        }
         match (
             &self.context.volatile.type_arena[t_idx],
             &self.context.volatile.type_arena[u_idx],
@@ -52,16 +73,6 @@ impl<'context> Entail<'context> {
             (Type::Constructor { name: t_name }, Type::Constructor { name: u_name }) => {
                 t_name == u_name
             }
-            (
-                Type::Variable {
-                    name: t_name,
-                    rank: t_rank,
-                },
-                Type::Variable {
-                    name: u_name,
-                    rank: u_rank,
-                },
-            ) => (t_name, t_rank) == (u_name, u_rank),
             (Type::Unification { name: t_name }, Type::Unification { name: u_name }) => {
                 t_name == u_name
             }
@@ -81,6 +92,11 @@ impl<'context> Entail<'context> {
                 let t_result = *t_result;
                 let u_result = *u_result;
 
+                let count = t_arguments.len().min(u_arguments.len());
+                let type_arena = &self.context.volatile.type_arena;
+                let t_result = applied(type_arena, &t_arguments, count, t_result);
+                let u_result = applied(type_arena, &u_arguments, count, u_result);
+
                 zip(t_arguments, u_arguments).all(|(t_argument, u_argument)| {
                     self.match_argument(substitutions, t_argument, u_argument)
                 }) && self.match_argument(substitutions, t_result, u_result)
@@ -106,8 +122,48 @@ impl<'context> Entail<'context> {
                         self.match_argument(substitutions, t_argument, u_argument)
                     })
             }
+            (
+                Type::Row {
+                    labels: t_labels,
+                    tail: t_tail,
+                },
+                Type::Row {
+                    labels: u_labels,
+                    tail: u_tail,
+                },
+            ) => {
+                if t_labels.len() != u_labels.len() {
+                    return false;
+                }
+
+                let t_labels = t_labels.clone();
+                let u_labels = u_labels.clone();
+
+                let t_tail = *t_tail;
+                let u_tail = *u_tail;
+
+                t_labels.into_iter().all(|(label, t_label)| {
+                    match u_labels.iter().find(|(u_label, _)| *u_label == label) {
+                        Some((_, u_label)) => self.match_argument(substitutions, t_label, *u_label),
+                        None => false,
+                    }
+                }) && match (t_tail, u_tail) {
+                    (Some(t_tail), Some(u_tail)) => {
+                        self.match_argument(substitutions, t_tail, u_tail)
+                    }
+                    (None, None) => true,
+                    _ => false,
+                }
+            }
+            (Type::Record { row: t_row }, Type::Record { row: u_row }) => {
+                let t_row = *t_row;
+                let u_row = *u_row;
+                self.match_argument(substitutions, t_row, u_row)
+            }
+            // NOTE: Type variables in the instance always bind, while the ones
+            // in the wanted assertion are rigid and only match themselves.
             (Type::Variable { name, .. }, _) => match substitutions.get(name) {
-                Some(t_idx) => self.match_argument(substitutions, *t_idx, u_idx),
+                Some(bound) => self.same_binding(*bound, u_idx),
                 None => {
                     substitutions.insert(name.clone(), u_idx);
                     true
@@ -118,25 +174,69 @@ impl<'context> Entail<'context> {
         }
     }
 
+    /// An instance variable that occurs more than once must be bound to the
+    /// same type each time, where unification variables may yet be solved
+    /// to be the same.
+    fn same_binding(&self, bound: TypeIdx, u_idx: TypeIdx) -> bool {
+        let mut zonk = Zonk::new(self.context);
+        let bound = zonk.zonk(bound);
+        let u_idx = zonk.zonk(u_idx);
+        bound == u_idx
+            || [bound, u_idx].into_iter().any(|t_idx| {
+                matches!(
+                    self.context.volatile.type_arena[t_idx],
+                    Type::Unification { .. }
+                )
+            })
+    }
+
+    /// Binds the instance variables of an argument that is identical to the
+    /// one it's matched against to themselves, failing if any was bound to
+    /// a different type already.
+    fn bind_identical(
+        &mut self,
+        substitutions: &mut HashMap<SmolStr, TypeIdx>,
+        t_idx: TypeIdx,
+    ) -> bool {
+        let mut variables = CollectVariables::new(self.context);
+        variables.traverse_ty(t_idx);
+        variables
+            .variables
+            .into_iter()
+            .all(|(name, v_idx)| *substitutions.entry(name).or_insert(v_idx) == v_idx)
+    }
+
+    /// Finds a given that is equal to `assertion` once both are zonked.
+    pub(crate) fn given(&self, assertion: &Assertion) -> Option<usize> {
+        let mut zonk = Zonk::new(self.context);
+        let assertion = zonk.zonk_assertion(assertion);
+        self.context
+            .givens
+            .iter()
+            .find_map(|(index, given)| (zonk.zonk_assertion(given) == assertion).then_some(*index))
+    }
+
     fn needs_solution(&self, assertion: &Assertion) -> HashSet<(usize, usize)> {
         let mut needs_solution = HashSet::new();
 
-        let class = self
+        // NOTE: Signatures that mention unknown classes are rejected by kind
+        // checking, so these are only ever left without instances.
+        let functional_dependencies = self
             .context
             .environment
             .classes
             .get(&assertion.name)
-            .cloned()
-            .unwrap();
+            .map(|class| class.functional_dependencies.clone())
+            .unwrap_or_default();
 
-        if class.functional_dependencies.is_empty() {
+        if functional_dependencies.is_empty() {
             for (index, argument) in assertion.arguments.iter().copied().enumerate() {
                 if let Type::Unification { name } = &self.context.volatile.type_arena[argument] {
                     needs_solution.insert((index, *name));
                 }
             }
         } else {
-            for FunctionalDependency { domain, .. } in &class.functional_dependencies {
+            for FunctionalDependency { domain, .. } in &functional_dependencies {
                 for argument_index in domain {
                     let argument = assertion.arguments[*argument_index];
                     if let Type::Unification { name } = &self.context.volatile.type_arena[argument]
@@ -156,13 +256,14 @@ impl<'context> Entail<'context> {
             return EntailResult::Deferred { needs_solution };
         }
 
-        let instances = self
-            .context
-            .environment
-            .instances
-            .get(&assertion.name)
-            .cloned()
-            .unwrap();
+        if let Some(index) = self.given(assertion) {
+            return EntailResult::Solved {
+                evidence: Evidence::Given { index },
+                instance_assertion: assertion.clone(),
+            };
+        }
+
+        let instances = self.context.environment.find_instances(&assertion.name);
 
         for instance in instances {
             let mut substitutions = HashMap::new();
@@ -231,13 +332,13 @@ impl<'context> Entail<'context> {
 }
 
 struct SubstituteGeneralizingFree<'context> {
-    context: &'context mut crate::context::Context,
+    context: &'context crate::context::Context,
     substitutions: &'context mut HashMap<SmolStr, TypeIdx>,
 }
 
 impl<'context> SubstituteGeneralizingFree<'context> {
     fn new(
-        context: &'context mut crate::context::Context,
+        context: &'context crate::context::Context,
         substitutions: &'context mut HashMap<SmolStr, TypeIdx>,
     ) -> Self {
         Self {
@@ -259,8 +360,12 @@ impl<'context> SubstituteGeneralizingFree<'context> {
 }
 
 impl<'context> Traversal for SubstituteGeneralizingFree<'context> {
-    fn arena(&mut self) -> &mut iwc_arena::Arena<Type> {
-        &mut self.context.volatile.type_arena
+    fn arena(&self) -> &iwc_arena::InternedArena<Type> {
+        &self.context.volatile.type_arena
+    }
+
+    fn spans(&self) -> &Spans<Type> {
+        &self.context.volatile.type_spans
     }
 
     fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
@@ -274,3 +379,36 @@ impl<'context> Traversal for SubstituteGeneralizingFree<'context> {
         }
     }
 }
+
+struct CollectVariables<'context> {
+    context: &'context crate::context::Context,
+    variables: Vec<(SmolStr, TypeIdx)>,
+}
+
+impl<'context> CollectVariables<'context> {
+    fn new(context: &'context crate::context::Context) -> Self {
+        Self {
+            context,
+            variables: vec![],
+        }
+    }
+}
+
+impl<'context> Traversal for CollectVariables<'context> {
+    fn arena(&self) -> &iwc_arena::InternedArena<Type> {
+        &self.context.volatile.type_arena
+    }
+
+    fn spans(&self) -> &Spans<Type> {
+        &self.context.volatile.type_spans
+    }
+
+    fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
+        if let Type::Variable { name, .. } = &self.context.volatile.type_arena[ty_idx] {
+            self.variables.push((name.clone(), ty_idx));
+            ty_idx
+        } else {
+            default_traverse_ty(self, ty_idx)
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use iwc_core_ast::{
+    expr::{ExprIdx, Literal, Pattern},
+    ty::{Type, TypeIdx},
+};
+use iwc_core_error::PatternError;
+use smol_str::SmolStr;
+
+use crate::context::Context;
+
+/// Checks case expressions for missing and redundant patterns, following
+/// Maranget's "Warnings for pattern matching".
+pub struct Exhaustive<'context> {
+    context: &'context Context,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Head {
+    Constructor(SmolStr),
+    Boolean(bool),
+    Literal(Literal),
+}
+
+impl<'context> Exhaustive<'context> {
+    pub fn new(context: &'context Context) -> Self {
+        Self { context }
+    }
+
+    pub fn check(&self, e_idx: ExprIdx, patterns: &[Pattern]) -> Vec<PatternError> {
+        let mut errors = vec![];
+
+        let mut rows = vec![];
+        for (index, pattern) in patterns.iter().enumerate() {
+            let row = vec![pattern.clone()];
+            if !self.is_useful(&rows, &row) {
+                errors.push(PatternError::Redundant(e_idx, index));
+            }
+            rows.push(row);
+        }
+
+        let missing: Vec<_> = self
+            .witnesses(&rows, 1)
+            .into_iter()
+            .map(|mut witness| witness.remove(0))
+            .collect();
+        if !missing.is_empty() {
+            errors.push(PatternError::NonExhaustive(e_idx, missing));
+        }
+
+        errors
+    }
+
+    fn is_useful(&self, rows: &[Vec<Pattern>], vector: &[Pattern]) -> bool {
+        let Some((first, rest)) = vector.split_first() else {
+            return rows.is_empty();
+        };
+        match head(first) {
+            Some((head, arguments)) => {
+                let rows = specialize(rows, &head, arguments.len());
+                let vector: Vec<_> = arguments.into_iter().chain(rest.iter().cloned()).collect();
+                self.is_useful(&rows, &vector)
+            }
+            None => match self.complete_signature(rows) {
+                Some(signature) => signature.into_iter().any(|(head, arity)| {
+                    let rows = specialize(rows, &head, arity);
+                    let vector: Vec<_> = wildcards(arity).chain(rest.iter().cloned()).collect();
+                    self.is_useful(&rows, &vector)
+                }),
+                None => self.is_useful(&default(rows), rest),
+            },
+        }
+    }
+
+    /// Computes the pattern vectors of the given `width` that aren't matched
+    /// by any of the `rows`.
+    fn witnesses(&self, rows: &[Vec<Pattern>], width: usize) -> Vec<Vec<Pattern>> {
+        if width == 0 {
+            return if rows.is_empty() {
+                vec![vec![]]
+            } else {
+                vec![]
+            };
+        }
+
+        if let Some(signature) = self.complete_signature(rows) {
+            return signature
+                .into_iter()
+                .flat_map(|(head, arity)| {
+                    self.witnesses(&specialize(rows, &head, arity), arity + width - 1)
+                        .into_iter()
+                        .map(move |mut witness| {
+                            let rest = witness.split_off(arity);
+                            let mut witness = vec![rebuild(&head, witness)];
+                            witness.extend(rest);
+                            witness
+                        })
+                })
+                .collect();
+        }
+
+        let rest = self.witnesses(&default(rows), width - 1);
+        if rest.is_empty() {
+            return vec![];
+        }
+
+        let heads = column_heads(rows);
+        let missing: Vec<_> = match heads.first().and_then(|head| self.signature(head)) {
+            Some(signature) => signature
+                .into_iter()
+                .filter(|(head, _)| !heads.contains(head))
+                .map(|(head, arity)| rebuild(&head, wildcards(arity).collect()))
+                .collect(),
+            None => vec![Pattern::Wildcard],
+        };
+
+        missing
+            .into_iter()
+            .flat_map(|missing| {
+                rest.iter().map(move |rest| {
+                    let mut witness = vec![missing.clone()];
+                    witness.extend(rest.iter().cloned());
+                    witness
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the signature of the first column if every one of its heads
+    /// is matched by some row.
+    fn complete_signature(&self, rows: &[Vec<Pattern>]) -> Option<Vec<(Head, usize)>> {
+        let heads = column_heads(rows);
+        let signature = self.signature(heads.first()?)?;
+        signature
+            .iter()
+            .all(|(head, _)| heads.contains(head))
+            .then_some(signature)
+    }
+
+    fn signature(&self, head: &Head) -> Option<Vec<(Head, usize)>> {
+        match head {
+            Head::Constructor(name) => {
+                let environment = &self.context.environment;
+                let data = environment.constructor_types.get(name)?;
+                environment.data[data]
+                    .iter()
+                    .map(|name| {
+                        let arity = self.arity(*environment.constructors.get(name)?);
+                        Some((Head::Constructor(name.clone()), arity))
+                    })
+                    .collect()
+            }
+            Head::Boolean(_) => Some(vec![(Head::Boolean(true), 0), (Head::Boolean(false), 0)]),
+            Head::Literal(_) => None,
+        }
+    }
+
+    fn arity(&self, t_idx: TypeIdx) -> usize {
+        match &self.context.volatile.type_arena[t_idx] {
+            Type::Function { arguments, result } => arguments.len() + self.arity(*result),
+            Type::Forall { ty, .. } | Type::Constrained { ty, .. } => self.arity(*ty),
+            _ => 0,
+        }
+    }
+}
+
+fn head(pattern: &Pattern) -> Option<(Head, Vec<Pattern>)> {
+    match pattern {
+        Pattern::Wildcard | Pattern::Variable { .. } => None,
+        Pattern::Literal {
+            literal: Literal::Boolean(value),
+        } => Some((Head::Boolean(*value), vec![])),
+        Pattern::Literal { literal } => Some((Head::Literal(literal.clone()), vec![])),
+        Pattern::Constructor { name, arguments } => Some((
+            Head::Constructor(name.clone()),
+            arguments.iter().cloned().collect(),
+        )),
+    }
+}
+
+fn rebuild(head: &Head, arguments: Vec<Pattern>) -> Pattern {
+    match head {
+        Head::Constructor(name) => Pattern::Constructor {
+            name: name.clone(),
+            arguments: arguments.into_iter().collect(),
+        },
+        Head::Boolean(value) => Pattern::Literal {
+            literal: Literal::Boolean(*value),
+        },
+        Head::Literal(literal) => Pattern::Literal {
+            literal: literal.clone(),
+        },
+    }
+}
+
+fn wildcards(arity: usize) -> impl Iterator<Item = Pattern> {
+    std::iter::repeat_n(Pattern::Wildcard, arity)
+}
+
+fn column_heads(rows: &[Vec<Pattern>]) -> Vec<Head> {
+    let mut heads = vec![];
+    for row in rows {
+        if let Some((head, _)) = head(&row[0]) {
+            if !heads.contains(&head) {
+                heads.push(head);
+            }
+        }
+    }
+    heads
+}
+
+fn specialize(rows: &[Vec<Pattern>], head: &Head, arity: usize) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter_map(|row| {
+            let arguments: Vec<_> = match self::head(&row[0]) {
+                None => wildcards(arity).collect(),
+                Some((row_head, arguments)) if &row_head == head => arguments,
+                Some(_) => return None,
+            };
+            Some(
+                arguments
+                    .into_iter()
+                    .chain(row[1..].iter().cloned())
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+fn default(rows: &[Vec<Pattern>]) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter(|row| head(&row[0]).is_none())
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
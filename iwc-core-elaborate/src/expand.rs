@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use iwc_core_ast::ty::{traversal::Traversal, Type, TypeIdx};
+
+use crate::{context::Context, instantiate::Substitute};
+
+/// Expands type synonyms at the head of a type, leaving the arguments as
+/// they were written such that solutions can keep the synonym names.
+pub(crate) struct Expand<'context> {
+    context: &'context Context,
+}
+
+impl<'context> Expand<'context> {
+    pub(crate) fn new(context: &'context Context) -> Self {
+        Self { context }
+    }
+
+    /// Returns the type unchanged if its head is not a synonym, or the
+    /// arity of the synonym and the number of arguments given to it if it
+    /// was only partially applied.
+    pub(crate) fn expand(&self, mut t_idx: TypeIdx) -> Result<TypeIdx, (usize, usize)> {
+        loop {
+            let (name, arguments) = match &self.context.volatile.type_arena[t_idx] {
+                Type::Constructor { name } => (name, vec![]),
+                Type::Application {
+                    function,
+                    arguments,
+                } => match &self.context.volatile.type_arena[*function] {
+                    Type::Constructor { name } => (name, arguments.iter().copied().collect()),
+                    _ => return Ok(t_idx),
+                },
+                _ => return Ok(t_idx),
+            };
+
+            let Some(synonym) = self.context.environment.synonyms.get(name) else {
+                return Ok(t_idx);
+            };
+
+            let arity = synonym.parameters.len();
+            if arguments.len() < arity {
+                return Err((arity, arguments.len()));
+            }
+
+            let substitutions: HashMap<_, _> = synonym
+                .parameters
+                .iter()
+                .map(|parameter| (parameter.clone(), 0))
+                .zip(arguments.iter().copied())
+                .collect();
+            let expanded = Substitute::new(self.context, substitutions).traverse_ty(synonym.ty);
+
+            // NOTE: Extra arguments are appended to a body that's already an
+            // application, as in `type Pair = Tuple Int` applied to `String`.
+            t_idx = if arguments.len() > arity {
+                let extra = arguments[arity..].iter().copied();
+                let (function, arguments) = match &self.context.volatile.type_arena[expanded] {
+                    Type::Application {
+                        function,
+                        arguments,
+                    } => (*function, arguments.iter().copied().chain(extra).collect()),
+                    _ => (expanded, extra.collect()),
+                };
+                self.context
+                    .volatile
+                    .type_arena
+                    .allocate(Type::Application {
+                        function,
+                        arguments,
+                    })
+            } else {
+                expanded
+            };
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet};
+
+use iwc_arena::InternedArena;
+use iwc_core_ast::{
+    span::Spans,
+    ty::{
+        traversal::{default_traverse_ty, Traversal},
+        Assertion, Type, TypeIdx, TypeVariableBinder,
+    },
+};
+use smol_str::SmolStr;
+
+use crate::{context::Context, zonk::Zonk};
+
+pub struct Generalize<'context> {
+    context: &'context mut Context,
+}
+
+impl<'context> Generalize<'context> {
+    pub fn new(context: &'context mut Context) -> Self {
+        Self { context }
+    }
+
+    /// Quantifies each type over the unification variables that don't occur
+    /// in the environment, moving the deferred assertions that mention them
+    /// into the resulting type. Types are generalized together such that
+    /// mutually recursive bindings can share their deferred assertions.
+    ///
+    /// Constraints are expected to be solved beforehand, and the deferred
+    /// assertions that don't mention a quantified variable are left behind
+    /// to be reported as unsolved.
+    pub fn generalize(&mut self, t_idxs: &[TypeIdx]) -> Vec<TypeIdx> {
+        let environment = self.environment_unifications();
+
+        let mut deferred: Vec<_> = std::mem::take(&mut self.context.solutions.entailment_deferred)
+            .into_iter()
+            .map(|deferred| {
+                let assertion = self.zonk_assertion(&deferred.assertion);
+                let mut unifications = vec![];
+                for argument in &assertion.arguments {
+                    self.collect(*argument, &mut unifications, &mut HashSet::new());
+                }
+                (deferred, assertion, unifications, false)
+            })
+            .collect();
+
+        let mut generalized = vec![];
+        for t_idx in t_idxs {
+            let ty_idx = Zonk::new(self.context).zonk(*t_idx);
+
+            let mut unifications = vec![];
+            let mut variables = HashSet::new();
+            self.collect(ty_idx, &mut unifications, &mut variables);
+            unifications.retain(|name| !environment.contains(name));
+
+            if unifications.is_empty() {
+                generalized.push(ty_idx);
+                continue;
+            }
+
+            let mut assertions = vec![];
+            for (_, assertion, assertion_unifications, used) in deferred.iter_mut() {
+                if assertion_unifications
+                    .iter()
+                    .any(|name| unifications.contains(name))
+                {
+                    for argument in &assertion.arguments {
+                        self.collect(*argument, &mut vec![], &mut variables);
+                    }
+                    for name in assertion_unifications.iter() {
+                        if !unifications.contains(name) && !environment.contains(name) {
+                            unifications.push(*name);
+                        }
+                    }
+                    assertions.push(assertion.clone());
+                    *used = true;
+                }
+            }
+
+            let mut names = fresh_names(&variables);
+            let binders: Vec<_> = unifications
+                .iter()
+                .map(|_| TypeVariableBinder {
+                    name: names.next().unwrap(),
+                    kind: None,
+                })
+                .collect();
+
+            let mut quantify = Quantify {
+                context: self.context,
+                substitutions: unifications
+                    .iter()
+                    .zip(&binders)
+                    .map(|(unification, TypeVariableBinder { name, .. })| {
+                        let variable = self.context.volatile.type_arena.allocate(Type::Variable {
+                            name: name.clone(),
+                            rank: 0,
+                        });
+                        (*unification, variable)
+                    })
+                    .collect(),
+            };
+
+            let mut ty = quantify.traverse_ty(ty_idx);
+            if !assertions.is_empty() {
+                let assertions = assertions
+                    .iter()
+                    .map(|assertion| quantify.traverse_assertion(assertion))
+                    .collect();
+                ty = self
+                    .context
+                    .volatile
+                    .type_arena
+                    .allocate(Type::Constrained { assertions, ty });
+            }
+
+            let forall = self.context.volatile.type_arena.allocate(Type::Forall {
+                variables: binders.into_iter().collect(),
+                rank: 0,
+                ty,
+            });
+            self.context.volatile.type_spans.inherit(*t_idx, forall);
+
+            generalized.push(forall);
+        }
+
+        self.context.solutions.entailment_deferred = deferred
+            .into_iter()
+            .filter_map(|(deferred, _, _, used)| (!used).then_some(deferred))
+            .collect();
+
+        generalized
+    }
+
+    fn environment_unifications(&self) -> HashSet<usize> {
+        let mut unifications = vec![];
+        for t_idx in self.context.environment.values.values() {
+            let t_idx = Zonk::new(self.context).zonk(*t_idx);
+            self.collect(t_idx, &mut unifications, &mut HashSet::new());
+        }
+        unifications.into_iter().collect()
+    }
+
+    fn zonk_assertion(&self, assertion: &Assertion) -> Assertion {
+        Zonk::new(self.context).zonk_assertion(assertion)
+    }
+
+    fn collect(
+        &self,
+        t_idx: TypeIdx,
+        unifications: &mut Vec<usize>,
+        variables: &mut HashSet<SmolStr>,
+    ) {
+        match &self.context.volatile.type_arena[t_idx] {
+            Type::Constructor { .. } => (),
+            Type::Variable { name, .. } => {
+                variables.insert(name.clone());
+            }
+            Type::Unification { name } => {
+                if !unifications.contains(name) {
+                    unifications.push(*name);
+                }
+            }
+            Type::Function { arguments, result } => {
+                for argument in arguments {
+                    self.collect(*argument, unifications, variables);
+                }
+                self.collect(*result, unifications, variables);
+            }
+            Type::Application {
+                function,
+                arguments,
+            } => {
+                self.collect(*function, unifications, variables);
+                for argument in arguments {
+                    self.collect(*argument, unifications, variables);
+                }
+            }
+            Type::Forall {
+                variables: binders,
+                ty,
+                ..
+            } => {
+                for TypeVariableBinder { name, .. } in binders {
+                    variables.insert(name.clone());
+                }
+                self.collect(*ty, unifications, variables);
+            }
+            Type::Constrained { assertions, ty } => {
+                for assertion in assertions {
+                    for argument in &assertion.arguments {
+                        self.collect(*argument, unifications, variables);
+                    }
+                }
+                self.collect(*ty, unifications, variables);
+            }
+            Type::Row { labels, tail } => {
+                for (_, label) in labels {
+                    self.collect(*label, unifications, variables);
+                }
+                if let Some(tail) = tail {
+                    self.collect(*tail, unifications, variables);
+                }
+            }
+            Type::Record { row } => self.collect(*row, unifications, variables),
+        }
+    }
+}
+
+/// Yields `a` through `z`, then `a1` through `z1` and so on, skipping the
+/// names that are already used by type variables.
+fn fresh_names(used: &HashSet<SmolStr>) -> impl Iterator<Item = SmolStr> + '_ {
+    (0..)
+        .flat_map(|suffix: usize| {
+            ('a'..='z').map(move |letter| {
+                if suffix == 0 {
+                    SmolStr::from(letter.to_string())
+                } else {
+                    SmolStr::from(format!("{}{}", letter, suffix))
+                }
+            })
+        })
+        .filter(|name| !used.contains(name))
+}
+
+struct Quantify<'context> {
+    context: &'context Context,
+    substitutions: HashMap<usize, TypeIdx>,
+}
+
+impl<'context> Traversal for Quantify<'context> {
+    fn arena(&self) -> &InternedArena<Type> {
+        &self.context.volatile.type_arena
+    }
+
+    fn spans(&self) -> &Spans<Type> {
+        &self.context.volatile.type_spans
+    }
+
+    fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
+        match &self.context.volatile.type_arena[ty_idx] {
+            Type::Unification { name } => match self.substitutions.get(name) {
+                Some(&variable) => variable,
+                None => ty_idx,
+            },
+            _ => default_traverse_ty(self, ty_idx),
+        }
+    }
+}
@@ -1,36 +1,69 @@
-use anyhow::Context;
-use im::Vector;
+use anyhow::{bail, Context};
+use im::{vector, Vector};
 use iwc_core_ast::{
-    expr::{Expr, ExprIdx},
-    ty::{Type, TypeIdx},
+    decl::{Module, ValueDecl},
+    expr::{CaseBranch, Expr, ExprIdx, Literal, Pattern},
+    kind::Kind,
+    ty::{Assertion, Type, TypeIdx},
 };
+use iwc_core_constraint::Constraint;
+use iwc_core_error::{InferError, UnifyError};
 use smol_str::SmolStr;
 
-use crate::{instantiate::Instantiate, solve::Solve, unify::Unify};
+use crate::{
+    dependency::binding_groups,
+    exhaustive::Exhaustive,
+    generalize::Generalize,
+    instantiate::Instantiate,
+    kind_check::KindCheck,
+    solve::Solve,
+    unify::{applied, Unify},
+};
 
 pub struct Infer<'context> {
     context: &'context mut crate::context::Context,
+    /// The innermost expression being inferred or checked, which the
+    /// constraints emitted along the way are attributed to.
+    origin: Option<ExprIdx>,
 }
 
 impl<'context> Infer<'context> {
     pub fn new(context: &'context mut crate::context::Context) -> Self {
-        Self { context }
+        Self {
+            context,
+            origin: None,
+        }
     }
 
     pub fn to_solve(self) -> Solve<'context> {
         Solve::new(self.context)
     }
 
-    pub fn as_unify<'infer>(&'infer mut self) -> Unify<'infer> {
-        Unify::new(self.context)
+    pub fn as_unify<'infer>(&'infer self) -> Unify<'infer> {
+        Unify::new(self.context).with_origin(self.origin)
     }
 
-    pub fn as_instantiate<'infer>(&'infer mut self) -> Instantiate<'infer> {
-        Instantiate::new(self.context)
+    pub fn as_instantiate<'infer>(&'infer self) -> Instantiate<'infer> {
+        Instantiate::new(self.context).with_origin(self.origin)
     }
 
+    /// Infers the type of `e_idx`, which is recorded for the expression
+    /// as it's returned, that is, before it's instantiated.
     pub fn infer(&mut self, e_idx: ExprIdx) -> anyhow::Result<TypeIdx> {
+        let origin = self.origin.replace(e_idx);
+        let t_idx = locate(e_idx, self.infer_expr(e_idx));
+        self.origin = origin;
+        let t_idx = t_idx?;
+        self.context.expr_types.insert(e_idx, t_idx);
+        Ok(t_idx)
+    }
+
+    fn infer_expr(&mut self, e_idx: ExprIdx) -> anyhow::Result<TypeIdx> {
         match &self.context.volatile.expr_arena[e_idx] {
+            Expr::Literal { literal } => {
+                let literal = literal.clone();
+                Ok(self.infer_literal(e_idx, &literal))
+            }
             Expr::Constructor { name } => self
                 .context
                 .environment
@@ -57,15 +90,20 @@ impl<'context> Infer<'context> {
 
                 let arguments: Vector<TypeIdx> = arguments
                     .into_iter()
-                    .map(|argument| self.infer(argument))
+                    .map(|argument| {
+                        let argument = self.infer(argument)?;
+                        Ok(self.as_instantiate().instantiate(argument))
+                    })
                     .collect::<anyhow::Result<_>>()?;
-                let result = self.context.fresh_unification();
+                let result = self.spanned(e_idx, self.context.fresh_unification());
 
-                let medium = self
-                    .context
-                    .volatile
-                    .type_arena
-                    .allocate(Type::Function { arguments, result });
+                let medium = self.spanned(
+                    e_idx,
+                    self.context
+                        .volatile
+                        .type_arena
+                        .allocate(Type::Function { arguments, result }),
+                );
 
                 self.as_unify().unify(function, medium);
 
@@ -77,41 +115,585 @@ impl<'context> Infer<'context> {
 
                 let variables: Vec<_> = arguments
                     .into_iter()
-                    .map(|name| (name, self.context.fresh_unification()))
+                    .map(|name| (name, self.spanned(e_idx, self.context.fresh_unification())))
                     .collect();
 
-                let result =
-                    self.with_unification_variables(&variables, |infer| infer.infer(body))?;
+                let result = self.with_values(&variables, |infer| infer.infer(body))?;
 
                 let arguments = variables
                     .into_iter()
                     .map(|(_, argument)| argument)
                     .collect();
 
-                Ok(self
-                    .context
-                    .volatile
-                    .type_arena
-                    .allocate(Type::Function { arguments, result }))
+                Ok(self.spanned(
+                    e_idx,
+                    self.context
+                        .volatile
+                        .type_arena
+                        .allocate(Type::Function { arguments, result }),
+                ))
+            }
+            Expr::Let { name, value, body } => {
+                let name = name.clone();
+                let value = *value;
+                let body = *body;
+
+                let value = self.infer(value)?;
+                let value = self.generalize(&[value])[0];
+
+                self.with_values(&[(name, value)], |infer| infer.infer(body))
+            }
+            Expr::LetRec { bindings, body } => {
+                let bindings = bindings.clone();
+                let body = *body;
+
+                let variables: Vec<_> = bindings
+                    .iter()
+                    .map(|(name, value)| {
+                        let unification = self.context.fresh_unification();
+                        (name.clone(), self.spanned(*value, unification))
+                    })
+                    .collect();
+
+                let values = self.with_values(&variables, |infer| {
+                    bindings
+                        .iter()
+                        .zip(&variables)
+                        .map(|((_, value), (_, unification))| {
+                            let value_type = infer.infer(*value)?;
+                            Ok(infer.at(*value, |infer| {
+                                let value_type = infer.as_instantiate().instantiate(value_type);
+                                infer.as_unify().unify(*unification, value_type);
+                                value_type
+                            }))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()
+                })?;
+
+                let values = self.generalize(&values);
+
+                let bindings: Vec<_> = variables
+                    .into_iter()
+                    .zip(values)
+                    .map(|((name, _), value)| (name, value))
+                    .collect();
+
+                self.with_values(&bindings, |infer| infer.infer(body))
+            }
+            Expr::Case {
+                scrutinee,
+                branches,
+            } => {
+                let scrutinee = *scrutinee;
+                let branches = branches.clone();
+
+                let result = self.spanned(e_idx, self.context.fresh_unification());
+                self.check_case(e_idx, scrutinee, branches, result)?;
+
+                Ok(result)
+            }
+            Expr::Annotation { expr, ty } => {
+                let expr = *expr;
+                let ty = *ty;
+
+                // NOTE: Ill-kinded annotations are reported but still checked
+                // against, as the expression was never meant to be unannotated.
+                let span = self.context.expr_span(e_idx);
+                if let Err(error) = KindCheck::new(self.context)
+                    .with_span(span)
+                    .check(ty, &Kind::Type)
+                {
+                    self.context.kind_errors.push(error);
+                }
+
+                self.check(expr, ty)?;
+
+                Ok(ty)
+            }
+            Expr::Record { fields } => {
+                let fields = fields.clone();
+
+                let labels = self.infer_fields(e_idx, &fields)?;
+
+                Ok(self.record(e_idx, labels, None))
+            }
+            Expr::Access { expr, label } => {
+                let expr = *expr;
+                let label = label.clone();
+
+                let expr = self.infer(expr)?;
+                let expr = self.as_instantiate().instantiate(expr);
+
+                let field = self.spanned(e_idx, self.context.fresh_unification());
+                let tail = self.context.fresh_unification();
+                let record = self.record(e_idx, vector![(label, field)], Some(tail));
+
+                self.as_unify().unify(expr, record);
+
+                Ok(field)
+            }
+            Expr::Update { expr, fields } => {
+                let expr = *expr;
+                let fields = fields.clone();
+
+                let expr = self.infer(expr)?;
+                let expr = self.as_instantiate().instantiate(expr);
+
+                let labels = self.infer_fields(e_idx, &fields)?;
+                let previous = labels
+                    .iter()
+                    .map(|(label, _)| (label.clone(), self.context.fresh_unification()))
+                    .collect();
+
+                let tail = self.context.fresh_unification();
+                let previous = self.record(e_idx, previous, Some(tail));
+
+                self.as_unify().unify(expr, previous);
+
+                Ok(self.record(e_idx, labels, Some(tail)))
             }
         }
     }
 
-    fn with_unification_variables<R>(
-        &mut self,
-        variables: &[(SmolStr, TypeIdx)],
-        action: impl FnOnce(&mut Self) -> R,
-    ) -> R {
-        for (variable, unification) in variables {
+    /// Infers the value declarations of a module one binding group at a
+    /// time, storing their generalized types in the environment.
+    pub fn infer_module(&mut self, module: &Module) -> anyhow::Result<()> {
+        let values: Vec<_> = module.values.iter().cloned().collect();
+        for group in binding_groups(&self.context.volatile.expr_arena, &values) {
+            let group: Vec<_> = group.into_iter().map(|index| &values[index]).collect();
+            self.infer_group(&group)?;
+        }
+        Ok(())
+    }
+
+    /// Mutually recursive declarations see each other's signatures, or
+    /// otherwise a shared monomorphic type until they're generalized.
+    fn infer_group(&mut self, group: &[&ValueDecl]) -> anyhow::Result<()> {
+        let mut variables = vec![];
+        for ValueDecl {
+            name,
+            signature,
+            signature_span,
+            value,
+        } in group.iter().copied()
+        {
+            let signature = signature.filter(|signature| {
+                match KindCheck::new(self.context)
+                    .with_span(signature_span.clone())
+                    .check(*signature, &Kind::Type)
+                {
+                    Ok(()) => true,
+                    Err(error) => {
+                        self.context.kind_errors.push(error);
+                        false
+                    }
+                }
+            });
+            let variable = match signature {
+                Some(signature) => signature,
+                None => self.spanned(*value, self.context.fresh_unification()),
+            };
             self.context
                 .environment
                 .values
-                .insert(variable.clone(), *unification);
+                .insert(name.clone(), variable);
+            variables.push((signature.is_some(), variable));
         }
+
+        let mut unsigned = vec![];
+        let mut failed = vec![];
+        for (ValueDecl { name, value, .. }, (signed, variable)) in group.iter().zip(variables) {
+            let result = if signed {
+                self.check(*value, variable)
+            } else {
+                self.infer(*value).map(|value_type| {
+                    let value_type = self.at(*value, |infer| {
+                        let value_type = infer.as_instantiate().instantiate(value_type);
+                        infer.as_unify().unify(variable, value_type);
+                        value_type
+                    });
+                    unsigned.push((name.clone(), value_type));
+                })
+            };
+            // NOTE: A binding that fails keeps the type it was given up front,
+            // such that the bindings that refer to it are still inferred.
+            if let Err(error) = result {
+                self.context.infer_errors.push(error.downcast()?);
+                if !signed {
+                    failed.push((name.clone(), variable));
+                }
+            }
+        }
+
+        // The monomorphic bindings would otherwise keep the group's own
+        // unification variables from being generalized.
+        for (name, _) in unsigned.iter().chain(&failed) {
+            self.context.environment.values.remove(name);
+        }
+
+        let values: Vec<_> = unsigned.iter().map(|(_, value)| *value).collect();
+        let values = self.generalize(&values);
+        let names = unsigned.into_iter().map(|(name, _)| name);
+        for (name, value) in names.zip(values).chain(failed) {
+            self.context.environment.values.insert(name, value);
+        }
+
+        Ok(())
+    }
+
+    /// Checks `e_idx` against an `expected` type, pushing it inwards such
+    /// that lambdas take their argument types from it. Expressions without
+    /// a checking rule are inferred and then unified with `expected`.
+    pub fn check(&mut self, e_idx: ExprIdx, expected: TypeIdx) -> anyhow::Result<()> {
+        let expected = match &self.context.volatile.type_arena[expected] {
+            Type::Forall { ty, .. } => *ty,
+            _ => expected,
+        };
+        if let Type::Constrained { assertions, ty } = &self.context.volatile.type_arena[expected] {
+            let assertions = assertions.clone();
+            let ty = *ty;
+            return self.check_given(e_idx, assertions, ty);
+        }
+        let origin = self.origin.replace(e_idx);
+        let checked = locate(e_idx, self.check_expr(e_idx, expected));
+        self.origin = origin;
+        checked?;
+        self.context.expr_types.insert(e_idx, expected);
+        Ok(())
+    }
+
+    /// Checks `e_idx` with the assertions of a constrained type as givens,
+    /// which the wanted assertions of the expression are solved against
+    /// before the givens go out of scope.
+    fn check_given(
+        &mut self,
+        e_idx: ExprIdx,
+        assertions: Vector<Assertion>,
+        expected: TypeIdx,
+    ) -> anyhow::Result<()> {
+        let scope = self.context.givens.len();
+        for assertion in assertions {
+            let index = self.context.fresh_index();
+            self.context.givens.push((index, assertion));
+        }
+
+        // NOTE: The wanted assertions are solved even if checking fails, as
+        // the givens aren't around to discharge them later.
+        let checked = self.check(e_idx, expected);
+        let mut solve = Solve::new(self.context);
+        solve.solve();
+        solve.discharge_givens();
+
+        self.context.givens.truncate(scope);
+        checked
+    }
+
+    fn check_expr(&mut self, e_idx: ExprIdx, expected: TypeIdx) -> anyhow::Result<()> {
+        match (
+            &self.context.volatile.expr_arena[e_idx],
+            &self.context.volatile.type_arena[expected],
+        ) {
+            (
+                Expr::Lambda { arguments, body },
+                Type::Function {
+                    arguments: expected_arguments,
+                    result,
+                },
+            ) if arguments.len() <= expected_arguments.len() => {
+                let variables: Vec<_> = arguments
+                    .iter()
+                    .cloned()
+                    .zip(expected_arguments.iter().copied())
+                    .collect();
+                let body = *body;
+                // A lambda that takes fewer arguments returns the function
+                // that takes the rest.
+                let result = applied(
+                    &self.context.volatile.type_arena,
+                    expected_arguments,
+                    arguments.len(),
+                    *result,
+                );
+
+                self.with_values(&variables, |infer| infer.check(body, result))
+            }
+            (Expr::Let { name, value, body }, _) => {
+                let name = name.clone();
+                let value = *value;
+                let body = *body;
+
+                let value = self.infer(value)?;
+                let value = self.generalize(&[value])[0];
+
+                self.with_values(&[(name, value)], |infer| infer.check(body, expected))
+            }
+            (
+                Expr::Case {
+                    scrutinee,
+                    branches,
+                },
+                _,
+            ) => {
+                let scrutinee = *scrutinee;
+                let branches = branches.clone();
+
+                self.check_case(e_idx, scrutinee, branches, expected)
+            }
+            _ => {
+                let inferred = self.infer(e_idx)?;
+                let inferred = self.as_instantiate().instantiate(inferred);
+                self.as_unify().unify(expected, inferred);
+                Ok(())
+            }
+        }
+    }
+
+    fn check_case(
+        &mut self,
+        e_idx: ExprIdx,
+        scrutinee: ExprIdx,
+        branches: Vector<CaseBranch>,
+        expected: TypeIdx,
+    ) -> anyhow::Result<()> {
+        let scrutinee = self.infer(scrutinee)?;
+        let scrutinee = self.as_instantiate().instantiate(scrutinee);
+
+        for CaseBranch { pattern, body } in &branches {
+            let mut bindings = vec![];
+            self.infer_pattern(e_idx, pattern, scrutinee, &mut bindings)?;
+            self.with_values(&bindings, |infer| infer.check(*body, expected))?;
+        }
+
+        let patterns: Vec<_> = branches
+            .into_iter()
+            .map(|CaseBranch { pattern, .. }| pattern)
+            .collect();
+        let errors = Exhaustive::new(self.context).check(e_idx, &patterns);
+        self.context.pattern_errors.extend(errors);
+
+        Ok(())
+    }
+
+    fn infer_pattern(
+        &mut self,
+        e_idx: ExprIdx,
+        pattern: &Pattern,
+        expected: TypeIdx,
+        bindings: &mut Vec<(SmolStr, TypeIdx)>,
+    ) -> anyhow::Result<()> {
+        match pattern {
+            Pattern::Wildcard => Ok(()),
+            Pattern::Variable { name } => {
+                if bindings.iter().any(|(bound, _)| bound == name) {
+                    bail!("Duplicate variable in pattern: {:?}", name);
+                }
+                bindings.push((name.clone(), expected));
+                Ok(())
+            }
+            Pattern::Literal { literal } => {
+                let literal = self.infer_literal(e_idx, literal);
+                self.as_unify().unify(expected, literal);
+                Ok(())
+            }
+            Pattern::Constructor { name, arguments } => {
+                let constructor = self
+                    .context
+                    .environment
+                    .constructors
+                    .get(name)
+                    .copied()
+                    .context(format!("Could not find constructor: {:?}", name))?;
+                let constructor = self.as_instantiate().instantiate(constructor);
+
+                let mut fields = vec![];
+                let mut result = constructor;
+                while let Type::Function {
+                    arguments,
+                    result: next,
+                } = &self.context.volatile.type_arena[result]
+                {
+                    fields.extend(arguments.iter().copied());
+                    result = *next;
+                }
+
+                if fields.len() != arguments.len() {
+                    bail!(
+                        "Constructor {:?} expects {} arguments, but was given {}",
+                        name,
+                        fields.len(),
+                        arguments.len()
+                    );
+                }
+
+                self.as_unify().unify(expected, result);
+                for (argument, field) in arguments.iter().zip(fields) {
+                    self.infer_pattern(e_idx, argument, field, bindings)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn infer_fields(
+        &mut self,
+        e_idx: ExprIdx,
+        fields: &Vector<(SmolStr, ExprIdx)>,
+    ) -> anyhow::Result<Vector<(SmolStr, TypeIdx)>> {
+        let labels: Vector<_> = fields
+            .iter()
+            .map(|(label, field)| {
+                let field = self.infer(*field)?;
+                Ok((label.clone(), self.as_instantiate().instantiate(field)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        for (index, (label, _)) in labels.iter().enumerate() {
+            if labels
+                .iter()
+                .take(index)
+                .any(|(previous, _)| previous == label)
+            {
+                let record = self.record(e_idx, labels.clone(), None);
+                self.context
+                    .constraints
+                    .push(Constraint::UnifyError(
+                        UnifyError::DuplicateLabel(record, label.clone()),
+                        Some(e_idx),
+                    ))
+                    .unwrap();
+                break;
+            }
+        }
+
+        Ok(labels)
+    }
+
+    fn record(
+        &self,
+        e_idx: ExprIdx,
+        labels: Vector<(SmolStr, TypeIdx)>,
+        tail: Option<TypeIdx>,
+    ) -> TypeIdx {
+        let row = self
+            .context
+            .volatile
+            .type_arena
+            .allocate(Type::Row { labels, tail });
+        self.spanned(
+            e_idx,
+            self.context
+                .volatile
+                .type_arena
+                .allocate(Type::Record { row }),
+        )
+    }
+
+    fn infer_literal(&mut self, e_idx: ExprIdx, literal: &Literal) -> TypeIdx {
+        let primitives = &self.context.environment.primitives;
+        let name = match literal {
+            Literal::Integer(_) => match &primitives.integer_class {
+                Some(class) => {
+                    let class = class.clone();
+                    return self.infer_overloaded_integer(e_idx, class);
+                }
+                None => primitives.integer.clone(),
+            },
+            Literal::Float(_) => primitives.float.clone(),
+            Literal::String(_) => primitives.string.clone(),
+            Literal::Char(_) => primitives.char.clone(),
+            Literal::Boolean(_) => primitives.boolean.clone(),
+        };
+        self.spanned(
+            e_idx,
+            self.context
+                .volatile
+                .type_arena
+                .allocate(Type::Constructor { name }),
+        )
+    }
+
+    /// Integer literals are given a fresh type that must be an instance of
+    /// the configured class, such that they can stand for any numeric type.
+    fn infer_overloaded_integer(&mut self, e_idx: ExprIdx, class: SmolStr) -> TypeIdx {
+        let t_idx = self.spanned(e_idx, self.context.fresh_unification());
+        let index = self.context.fresh_index();
+        self.context
+            .constraints
+            .push(Constraint::ClassEntail(
+                index,
+                Assertion {
+                    name: class,
+                    arguments: vector![t_idx],
+                },
+                Some(e_idx),
+            ))
+            .unwrap();
+        t_idx
+    }
+
+    /// Solves the constraints emitted so far, such that the types can be
+    /// generalized over the unification variables that remain unsolved.
+    pub fn generalize(&mut self, t_idxs: &[TypeIdx]) -> Vec<TypeIdx> {
+        Solve::new(self.context).solve();
+        Generalize::new(self.context).generalize(t_idxs)
+    }
+
+    /// Attributes the constraints emitted by `f` to `e_idx`, such as the
+    /// ones relating a binding to its value once the value is inferred.
+    fn at<R>(&mut self, e_idx: ExprIdx, f: impl FnOnce(&mut Self) -> R) -> R {
+        let origin = self.origin.replace(e_idx);
+        let result = f(self);
+        self.origin = origin;
+        result
+    }
+
+    /// Records the span of `e_idx` as the origin of a type synthesized for it.
+    fn spanned(&self, e_idx: ExprIdx, t_idx: TypeIdx) -> TypeIdx {
+        if let Some(span) = self.context.volatile.expr_spans.get(e_idx) {
+            self.context
+                .volatile
+                .type_spans
+                .insert_if_missing(t_idx, span);
+        }
+        t_idx
+    }
+
+    fn with_values<R>(
+        &mut self,
+        values: &[(SmolStr, TypeIdx)],
+        action: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let shadowed: Vec<_> = values
+            .iter()
+            .map(|(name, t_idx)| {
+                let shadowed = self.context.environment.values.insert(name.clone(), *t_idx);
+                (name.clone(), shadowed)
+            })
+            .collect();
         let result = action(self);
-        for (variable, _) in variables {
-            self.context.environment.values.remove(variable);
+        for (name, shadowed) in shadowed.into_iter().rev() {
+            match shadowed {
+                Some(t_idx) => self.context.environment.values.insert(name, t_idx),
+                None => self.context.environment.values.remove(&name),
+            };
         }
         result
     }
 }
+
+/// Attributes an error to `e_idx` unless it was already attributed to one
+/// of the expressions within.
+fn locate<T>(e_idx: ExprIdx, result: anyhow::Result<T>) -> anyhow::Result<T> {
+    result.map_err(|error| {
+        if error.is::<InferError>() {
+            error
+        } else {
+            InferError {
+                origin: e_idx,
+                message: error.to_string(),
+            }
+            .into()
+        }
+    })
+}
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 
-use iwc_arena::Arena;
-use iwc_core_ast::ty::{
-    traversal::{default_traverse_ty, Traversal},
-    Assertion, Type, TypeIdx, TypeVariableBinder,
+use iwc_arena::InternedArena;
+use iwc_core_ast::{
+    expr::ExprIdx,
+    span::Spans,
+    ty::{
+        traversal::{default_traverse_ty, Traversal},
+        Assertion, Type, TypeIdx, TypeVariableBinder,
+    },
 };
 use iwc_core_constraint::Constraint;
 use smol_str::SmolStr;
@@ -11,12 +15,22 @@ use smol_str::SmolStr;
 use crate::context::Context;
 
 pub struct Instantiate<'context> {
-    context: &'context mut Context,
+    context: &'context Context,
+    origin: Option<ExprIdx>,
 }
 
 impl<'context> Instantiate<'context> {
-    pub fn new(context: &'context mut Context) -> Self {
-        Self { context }
+    pub fn new(context: &'context Context) -> Self {
+        Self {
+            context,
+            origin: None,
+        }
+    }
+
+    /// Attributes the assertions emitted from here on to `origin`.
+    pub fn with_origin(mut self, origin: Option<ExprIdx>) -> Self {
+        self.origin = origin;
+        self
     }
 
     pub fn instantiate(&mut self, t_idx: TypeIdx) -> TypeIdx {
@@ -30,14 +44,23 @@ impl<'context> Instantiate<'context> {
             let rank = *rank;
             let ty_idx = *ty;
 
-            if let Type::Constrained { assertions, ty } = &self.context.volatile.type_arena[ty_idx]
+            let mut substitute =
+                Substitute::from_type_variable_binders(self.context, variables, rank);
+
+            // Fresh unification variables point back to the quantified type.
+            for unification in substitute.substitutions.values() {
+                self.context
+                    .volatile
+                    .type_spans
+                    .inherit(t_idx, *unification);
+            }
+
+            let result = if let Type::Constrained { assertions, ty } =
+                &self.context.volatile.type_arena[ty_idx]
             {
                 let mut assertions = assertions.clone();
                 let ty_idx = *ty;
 
-                let mut substitute =
-                    Substitute::from_type_variable_binders(self.context, variables, rank);
-
                 for assertion in assertions.iter_mut() {
                     *assertion = substitute.traverse_assertion(assertion);
                 }
@@ -49,31 +72,48 @@ impl<'context> Instantiate<'context> {
 
                 ty_idx
             } else {
-                Substitute::from_type_variable_binders(self.context, variables, rank)
-                    .traverse_ty(ty_idx)
-            }
+                substitute.traverse_ty(ty_idx)
+            };
+
+            self.context.volatile.type_spans.inherit(t_idx, result);
+
+            result
         } else {
             t_idx
         }
     }
 
+    /// Replaces the type variables in an assertion with fresh unification
+    /// variables, which are returned along with the names they replace.
+    pub fn instantiate_assertion(
+        &mut self,
+        assertion: &Assertion,
+    ) -> (Assertion, Vec<(SmolStr, TypeIdx)>) {
+        let mut freshen = Freshen {
+            context: self.context,
+            unifications: vec![],
+        };
+        let assertion = freshen.traverse_assertion(assertion);
+        (assertion, freshen.unifications)
+    }
+
     fn emit_entail(&mut self, assertion: Assertion) {
         let index = self.context.fresh_index();
         self.context
             .constraints
-            .push(Constraint::ClassEntail(index, assertion))
+            .push(Constraint::ClassEntail(index, assertion, self.origin))
             .unwrap()
     }
 }
 
-struct Substitute<'context> {
-    context: &'context mut Context,
+pub(crate) struct Substitute<'context> {
+    context: &'context Context,
     substitutions: HashMap<(SmolStr, usize), TypeIdx>,
 }
 
 impl<'context> Substitute<'context> {
-    fn new(
-        context: &'context mut Context,
+    pub(crate) fn new(
+        context: &'context Context,
         substitutions: HashMap<(SmolStr, usize), TypeIdx>,
     ) -> Self {
         Self {
@@ -82,17 +122,13 @@ impl<'context> Substitute<'context> {
         }
     }
 
-    fn from_type_variable_binders<V>(
-        context: &'context mut Context,
-        variables: V,
-        rank: usize,
-    ) -> Self
+    fn from_type_variable_binders<V>(context: &'context Context, variables: V, rank: usize) -> Self
     where
         V: IntoIterator<Item = TypeVariableBinder>,
     {
         let substitutions = variables
             .into_iter()
-            .map(|TypeVariableBinder { name }| {
+            .map(|TypeVariableBinder { name, .. }| {
                 let index = context.fresh_unification();
                 ((name, rank), index)
             })
@@ -102,8 +138,12 @@ impl<'context> Substitute<'context> {
 }
 
 impl<'context> Traversal for Substitute<'context> {
-    fn arena(&mut self) -> &mut Arena<Type> {
-        &mut self.context.volatile.type_arena
+    fn arena(&self) -> &InternedArena<Type> {
+        &self.context.volatile.type_arena
+    }
+
+    fn spans(&self) -> &Spans<Type> {
+        &self.context.volatile.type_spans
     }
 
     fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
@@ -119,3 +159,40 @@ impl<'context> Traversal for Substitute<'context> {
         }
     }
 }
+
+struct Freshen<'context> {
+    context: &'context Context,
+    unifications: Vec<(SmolStr, TypeIdx)>,
+}
+
+impl<'context> Traversal for Freshen<'context> {
+    fn arena(&self) -> &InternedArena<Type> {
+        &self.context.volatile.type_arena
+    }
+
+    fn spans(&self) -> &Spans<Type> {
+        &self.context.volatile.type_spans
+    }
+
+    fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
+        match &self.context.volatile.type_arena[ty_idx] {
+            Type::Variable { name, .. } => {
+                if let Some((_, unification)) = self
+                    .unifications
+                    .iter()
+                    .find(|(variable, _)| variable == name)
+                {
+                    return *unification;
+                }
+                let unification = self.context.fresh_unification();
+                self.context
+                    .volatile
+                    .type_spans
+                    .inherit(ty_idx, unification);
+                self.unifications.push((name.clone(), unification));
+                unification
+            }
+            _ => default_traverse_ty(self, ty_idx),
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+
+use iwc_core_ast::{
+    decl::DataDecl,
+    kind::Kind,
+    span::Span,
+    ty::{Type, TypeIdx, TypeVariableBinder},
+};
+use iwc_core_error::KindError;
+use smol_str::SmolStr;
+
+use crate::{context::Context, expand::Expand};
+
+pub struct KindCheck<'context> {
+    context: &'context Context,
+    span: Option<Span>,
+    fresh: usize,
+    variables: HashMap<SmolStr, Kind>,
+    signatures: HashMap<SmolStr, Kind>,
+    unifications: HashMap<usize, Kind>,
+    solved: HashMap<usize, Kind>,
+}
+
+impl<'context> KindCheck<'context> {
+    pub fn new(context: &'context Context) -> Self {
+        Self {
+            context,
+            span: None,
+            fresh: 0,
+            variables: HashMap::new(),
+            signatures: HashMap::new(),
+            unifications: HashMap::new(),
+            solved: HashMap::new(),
+        }
+    }
+
+    /// Reports the errors from here on at `span`, which is where the type
+    /// being checked was written.
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn check(&mut self, t_idx: TypeIdx, expected: &Kind) -> Result<(), KindError> {
+        let kind = self.infer_kind(t_idx)?;
+        self.unify(t_idx, &kind, expected)
+    }
+
+    pub fn infer(&mut self, t_idx: TypeIdx) -> Result<Kind, KindError> {
+        let kind = self.infer_kind(t_idx)?;
+        Ok(self.zonk(&kind))
+    }
+
+    /// Infers the kinds of the type parameters of a data declaration from
+    /// its fields, where the declared type is in scope for recursive fields.
+    /// Parameters that are left unconstrained default to `Type`.
+    pub fn infer_data(&mut self, data: &DataDecl) -> Result<Vec<Kind>, KindError> {
+        let mut substitutions = HashMap::new();
+        let kinds: Vec<_> = data
+            .type_params
+            .iter()
+            .map(|TypeVariableBinder { kind, .. }| match kind {
+                Some(kind) => self.instantiate(kind, &mut substitutions),
+                None => self.fresh(),
+            })
+            .collect();
+
+        let kind = kinds.iter().rev().fold(Kind::Type, |result, argument| {
+            Kind::arrow(argument.clone(), result)
+        });
+        self.signatures.insert(data.name.clone(), kind);
+        for (TypeVariableBinder { name, .. }, kind) in data.type_params.iter().zip(&kinds) {
+            self.variables.insert(name.clone(), kind.clone());
+        }
+
+        let result = data
+            .constructors
+            .iter()
+            .flat_map(|(_, fields)| fields)
+            .try_for_each(|field| self.check(*field, &Kind::Type));
+
+        self.signatures.remove(&data.name);
+        for TypeVariableBinder { name, .. } in &data.type_params {
+            self.variables.remove(name);
+        }
+        result?;
+
+        Ok(kinds.iter().map(|kind| self.default(kind)).collect())
+    }
+
+    fn infer_kind(&mut self, t_idx: TypeIdx) -> Result<Kind, KindError> {
+        match Expand::new(self.context).expand(t_idx) {
+            Ok(expanded) if expanded != t_idx => return self.infer_kind(expanded),
+            Ok(_) => (),
+            Err((arity, given)) => {
+                return Err(KindError::PartialSynonym(
+                    self.span.clone(),
+                    t_idx,
+                    arity,
+                    given,
+                ))
+            }
+        }
+        match &self.context.volatile.type_arena[t_idx] {
+            Type::Constructor { name } => {
+                let kind = self.constructor(name).ok_or_else(|| {
+                    KindError::UnknownConstructor(self.span.clone(), t_idx, name.clone())
+                })?;
+                Ok(self.instantiate(&kind, &mut HashMap::new()))
+            }
+            Type::Variable { name, .. } => {
+                if let Some(kind) = self.variables.get(name) {
+                    return Ok(kind.clone());
+                }
+                let kind = self.fresh();
+                self.variables.insert(name.clone(), kind.clone());
+                Ok(kind)
+            }
+            Type::Unification { name } => {
+                if let Some(kind) = self.unifications.get(name) {
+                    return Ok(kind.clone());
+                }
+                let kind = self.fresh();
+                self.unifications.insert(*name, kind.clone());
+                Ok(kind)
+            }
+            Type::Function { arguments, result } => {
+                let arguments = arguments.clone();
+                let result = *result;
+
+                for argument in arguments {
+                    self.check(argument, &Kind::Type)?;
+                }
+                self.check(result, &Kind::Type)?;
+
+                Ok(Kind::Type)
+            }
+            Type::Application {
+                function,
+                arguments,
+            } => {
+                let function = *function;
+                let arguments = arguments.clone();
+
+                let mut kind = self.infer_kind(function)?;
+                for argument in arguments {
+                    let argument_kind = self.infer_kind(argument)?;
+                    let result = self.fresh();
+                    self.unify(t_idx, &kind, &Kind::arrow(argument_kind, result.clone()))?;
+                    kind = result;
+                }
+
+                Ok(kind)
+            }
+            Type::Forall { variables, ty, .. } => {
+                let variables = variables.clone();
+                let ty = *ty;
+
+                let mut substitutions = HashMap::new();
+                let mut shadowed = vec![];
+                for TypeVariableBinder { name, kind } in variables {
+                    let kind = match kind {
+                        Some(kind) => self.instantiate(&kind, &mut substitutions),
+                        None => self.fresh(),
+                    };
+                    shadowed.push((name.clone(), self.variables.insert(name, kind)));
+                }
+
+                let result = self.check(ty, &Kind::Type);
+
+                for (name, shadowed) in shadowed.into_iter().rev() {
+                    match shadowed {
+                        Some(kind) => self.variables.insert(name, kind),
+                        None => self.variables.remove(&name),
+                    };
+                }
+
+                result.map(|_| Kind::Type)
+            }
+            Type::Constrained { assertions, ty } => {
+                let assertions = assertions.clone();
+                let ty = *ty;
+
+                for assertion in assertions {
+                    self.context
+                        .environment
+                        .check_class(&assertion, self.span.clone())?;
+                    for argument in assertion.arguments {
+                        self.infer_kind(argument)?;
+                    }
+                }
+                self.check(ty, &Kind::Type)?;
+
+                Ok(Kind::Type)
+            }
+            Type::Row { labels, tail } => {
+                let labels = labels.clone();
+                let tail = *tail;
+
+                let element = self.fresh();
+                for (_, label) in labels {
+                    self.check(label, &element)?;
+                }
+                let kind = Kind::Row(Box::new(element));
+                if let Some(tail) = tail {
+                    self.check(tail, &kind)?;
+                }
+
+                Ok(kind)
+            }
+            Type::Record { row } => {
+                let row = *row;
+
+                self.check(row, &Kind::Row(Box::new(Kind::Type)))?;
+
+                Ok(Kind::Type)
+            }
+        }
+    }
+
+    /// Primitive types are assumed to be of kind `Type` unless they've been
+    /// given a signature of their own.
+    fn constructor(&self, name: &SmolStr) -> Option<Kind> {
+        if let Some(kind) = self.signatures.get(name) {
+            return Some(kind.clone());
+        }
+        let environment = &self.context.environment;
+        if let Some(kind) = environment.kinds.get(name) {
+            return Some(kind.clone());
+        }
+        let primitives = &environment.primitives;
+        [
+            &primitives.integer,
+            &primitives.float,
+            &primitives.string,
+            &primitives.char,
+            &primitives.boolean,
+        ]
+        .contains(&name)
+        .then_some(Kind::Type)
+    }
+
+    fn instantiate(&mut self, kind: &Kind, substitutions: &mut HashMap<usize, Kind>) -> Kind {
+        match kind {
+            Kind::Type => Kind::Type,
+            Kind::Row(kind) => Kind::Row(Box::new(self.instantiate(kind, substitutions))),
+            Kind::Arrow(argument, result) => {
+                let argument = self.instantiate(argument, substitutions);
+                let result = self.instantiate(result, substitutions);
+                Kind::arrow(argument, result)
+            }
+            Kind::Variable(name) => {
+                if let Some(kind) = substitutions.get(name) {
+                    return kind.clone();
+                }
+                let kind = self.fresh();
+                substitutions.insert(*name, kind.clone());
+                kind
+            }
+        }
+    }
+
+    fn unify(&mut self, t_idx: TypeIdx, t_kind: &Kind, u_kind: &Kind) -> Result<(), KindError> {
+        let t_kind = self.resolve(t_kind);
+        let u_kind = self.resolve(u_kind);
+        match (&t_kind, &u_kind) {
+            (Kind::Type, Kind::Type) => Ok(()),
+            (Kind::Variable(t_name), Kind::Variable(u_name)) if t_name == u_name => Ok(()),
+            (Kind::Variable(name), kind) | (kind, Kind::Variable(name)) => {
+                if self.occurs_check(*name, kind) {
+                    return Err(KindError::InfiniteKind(
+                        self.span.clone(),
+                        t_idx,
+                        *name,
+                        self.zonk(kind),
+                    ));
+                }
+                self.solved.insert(*name, kind.clone());
+                Ok(())
+            }
+            (Kind::Row(t_kind), Kind::Row(u_kind)) => self.unify(t_idx, t_kind, u_kind),
+            (Kind::Arrow(t_argument, t_result), Kind::Arrow(u_argument, u_result)) => {
+                self.unify(t_idx, t_argument, u_argument)?;
+                self.unify(t_idx, t_result, u_result)
+            }
+            _ => Err(KindError::CannotUnify(
+                self.span.clone(),
+                t_idx,
+                self.zonk(&t_kind),
+                self.zonk(&u_kind),
+            )),
+        }
+    }
+
+    fn occurs_check(&self, name: usize, kind: &Kind) -> bool {
+        match self.resolve(kind) {
+            Kind::Type => false,
+            Kind::Row(kind) => self.occurs_check(name, &kind),
+            Kind::Arrow(argument, result) => {
+                self.occurs_check(name, &argument) || self.occurs_check(name, &result)
+            }
+            Kind::Variable(variable) => variable == name,
+        }
+    }
+
+    fn resolve(&self, kind: &Kind) -> Kind {
+        match kind {
+            Kind::Variable(name) => match self.solved.get(name) {
+                Some(kind) => self.resolve(kind),
+                None => kind.clone(),
+            },
+            _ => kind.clone(),
+        }
+    }
+
+    fn zonk(&self, kind: &Kind) -> Kind {
+        match self.resolve(kind) {
+            Kind::Row(kind) => Kind::Row(Box::new(self.zonk(&kind))),
+            Kind::Arrow(argument, result) => Kind::arrow(self.zonk(&argument), self.zonk(&result)),
+            kind => kind,
+        }
+    }
+
+    fn default(&self, kind: &Kind) -> Kind {
+        match self.resolve(kind) {
+            Kind::Type | Kind::Variable(_) => Kind::Type,
+            Kind::Row(kind) => Kind::Row(Box::new(self.default(&kind))),
+            Kind::Arrow(argument, result) => {
+                Kind::arrow(self.default(&argument), self.default(&result))
+            }
+        }
+    }
+
+    fn fresh(&mut self) -> Kind {
+        self.fresh += 1;
+        Kind::Variable(self.fresh - 1)
+    }
+}
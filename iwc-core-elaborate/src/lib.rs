@@ -1,20 +1,45 @@
 pub mod context;
+pub mod declare;
+pub(crate) mod dependency;
 pub mod entail;
+pub mod exhaustive;
+pub(crate) mod expand;
+pub mod generalize;
 pub mod infer;
 pub mod instantiate;
+pub mod kind_check;
 pub mod solve;
+pub mod substitution;
 pub mod unify;
+pub mod zonk;
 
 #[cfg(test)]
 mod tests {
-    use im::vector;
-    use iwc_core_ast::ty::{
-        pretty::{pretty_print_assertion, pretty_print_ty},
-        Assertion, Class, FunctionalDependency, Instance, Type,
+    use im::{vector, Vector};
+    use iwc_core_ast::{
+        decl::{DataDecl, Module, ValueDecl},
+        expr::{pretty::pretty_print_expr_with_types, CaseBranch, Expr, Literal, Pattern},
+        kind::Kind,
+        span::Span,
+        ty::{
+            pretty::{pretty_print_assertion, pretty_print_ty, PrettyOptions, TypePrinter},
+            Assertion, Class, FunctionalDependency, Instance, Synonym, Type, TypeVariableBinder,
+        },
     };
     use iwc_core_constraint::Constraint;
+    use iwc_core_error::{KindError, PatternError, UnifyError};
+    use iwc_parser::{parse_module, parse_type, Arenas};
 
-    use crate::{context::Context, solve::Solve};
+    use crate::{
+        context::{Context, Primitives},
+        declare::Declare,
+        expand::Expand,
+        infer::Infer,
+        kind_check::KindCheck,
+        solve::Solve,
+        unify::Unify,
+        zonk::Zonk,
+    };
 
     #[test]
     fn entailment_concrete() {
@@ -28,6 +53,7 @@ mod tests {
         context.environment.classes.insert(
             "Eq".into(),
             Class {
+                arity: 1,
                 functional_dependencies: vector![],
             },
         );
@@ -40,6 +66,7 @@ mod tests {
                     arguments: vector![int],
                 },
                 dependencies: vector![],
+                span: None,
             }],
         );
 
@@ -52,6 +79,7 @@ mod tests {
                     name: "Eq".into(),
                     arguments: vector![int],
                 },
+                None,
             ))
             .unwrap();
 
@@ -59,7 +87,7 @@ mod tests {
 
         solve.step();
 
-        dbg!(&solve.entailment_evidences);
+        dbg!(&solve.context.solutions.entailment_evidences);
     }
 
     #[test]
@@ -85,12 +113,14 @@ mod tests {
                     arguments: vector![int],
                 },
                 dependencies: vector![],
+                span: None,
             }],
         );
 
         context.environment.classes.insert(
             "Eq".into(),
             Class {
+                arity: 1,
                 functional_dependencies: vector![],
             },
         );
@@ -105,25 +135,26 @@ mod tests {
                     name: "Eq".into(),
                     arguments: vector![u_one],
                 },
+                None,
             ))
             .unwrap();
 
         context
             .constraints
-            .push(Constraint::UnifySolve(u_one_name, int))
+            .push(Constraint::UnifySolve(u_one_name, int, None))
             .unwrap();
 
         let mut solve = Solve::new(context);
 
         solve.step();
 
-        dbg!(&solve.unification_solved);
-        dbg!(&solve.entailment_evidences);
+        dbg!(&solve.context.solutions.substitution);
+        dbg!(&solve.context.solutions.entailment_evidences);
 
         solve.step();
 
-        dbg!(&solve.unification_solved);
-        dbg!(&solve.entailment_evidences);
+        dbg!(&solve.context.solutions.substitution);
+        dbg!(&solve.context.solutions.entailment_evidences);
     }
 
     #[test]
@@ -133,6 +164,7 @@ mod tests {
         context.environment.classes.insert(
             "Eq".into(),
             Class {
+                arity: 1,
                 functional_dependencies: vector![],
             },
         );
@@ -168,6 +200,7 @@ mod tests {
                         name: "Eq".into(),
                         arguments: vector![a],
                     }],
+                    span: None,
                 },
                 Instance {
                     assertion: Assertion {
@@ -175,6 +208,7 @@ mod tests {
                         arguments: vector![int],
                     },
                     dependencies: vector![],
+                    span: None,
                 },
             ],
         );
@@ -193,6 +227,7 @@ mod tests {
                     name: "Eq".into(),
                     arguments: vector![array_int],
                 },
+                None,
             ))
             .unwrap();
 
@@ -200,7 +235,7 @@ mod tests {
 
         solve.step();
 
-        dbg!(&solve.entailment_evidences);
+        dbg!(&solve.context.solutions.entailment_evidences);
     }
 
     #[test]
@@ -210,6 +245,7 @@ mod tests {
         context.environment.classes.insert(
             "Append".into(),
             Class {
+                arity: 3,
                 functional_dependencies: vector![FunctionalDependency {
                     domain: vector![0, 1],
                     codomain: vector![2],
@@ -269,6 +305,7 @@ mod tests {
                             arguments: vector![nil, ys, ys],
                         },
                         dependencies: vector![],
+                        span: None,
                     },
                     Instance {
                         assertion: Assertion {
@@ -279,6 +316,7 @@ mod tests {
                             name: "Append".into(),
                             arguments: vector![xs, ys, zs],
                         }],
+                        span: None,
                     },
                 ],
             );
@@ -314,19 +352,1309 @@ mod tests {
         let index = context.fresh_index();
         context
             .constraints
-            .push(Constraint::ClassEntail(index, assertion))
+            .push(Constraint::ClassEntail(index, assertion, None))
             .unwrap();
 
         let mut solve = Solve::new(&mut context);
 
         solve.step();
 
-        for (u, t_idx) in &solve.unification_solved {
-            println!(
-                "?{} ~ {}",
-                u,
-                pretty_print_ty(&solve.context.volatile.type_arena, *t_idx)
+        let substitution = &solve.context.solutions.substitution;
+        for u in substitution.names() {
+            if let Some(t_idx) = substitution.solution(u) {
+                println!(
+                    "?{} ~ {}",
+                    u,
+                    pretty_print_ty(&solve.context.volatile.type_arena, t_idx)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn entailment_no_instance() {
+        let context = &mut Context::default();
+
+        let int = context
+            .volatile
+            .type_arena
+            .allocate(Type::Constructor { name: "Int".into() });
+
+        context.environment.instances.insert("Eq".into(), vec![]);
+        context.environment.classes.insert(
+            "Eq".into(),
+            Class {
+                arity: 1,
+                functional_dependencies: vector![],
+            },
+        );
+
+        let index = context.fresh_index();
+        context
+            .constraints
+            .push(Constraint::ClassEntail(
+                index,
+                Assertion {
+                    name: "Eq".into(),
+                    arguments: vector![int],
+                },
+                None,
+            ))
+            .unwrap();
+
+        let mut solve = Solve::new(context);
+        solve.solve();
+
+        assert!(solve.context.solutions.entailment_evidences.is_empty());
+        assert_eq!(solve.context.solutions.entailment_deferred.len(), 1);
+    }
+
+    #[test]
+    fn unification_concurrent() {
+        let context = &mut Context::default();
+
+        let int = context
+            .volatile
+            .type_arena
+            .allocate(Type::Constructor { name: "Int".into() });
+
+        let unifications: Vec<_> = (0..4).map(|_| context.fresh_unification()).collect();
+
+        std::thread::scope(|scope| {
+            for u in &unifications {
+                let context = &*context;
+                scope.spawn(move || {
+                    let array = context.volatile.type_arena.allocate(Type::Constructor {
+                        name: "Array".into(),
+                    });
+                    let array_u = context.volatile.type_arena.allocate(Type::Application {
+                        function: array,
+                        arguments: vector![*u],
+                    });
+                    let array_int = context.volatile.type_arena.allocate(Type::Application {
+                        function: array,
+                        arguments: vector![int],
+                    });
+                    Unify::new(context).unify(array_u, array_int);
+                });
+            }
+        });
+
+        let mut solve = Solve::new(context);
+
+        solve.solve();
+
+        for u in unifications {
+            let Type::Unification { name } = solve.context.volatile.type_arena[u] else {
+                unreachable!();
+            };
+            assert_eq!(
+                solve.context.solutions.substitution.solution(name),
+                Some(int)
             );
         }
     }
+
+    #[test]
+    fn unification_union_find() {
+        let context = &mut Context::default();
+
+        let int = context
+            .volatile
+            .type_arena
+            .allocate(Type::Constructor { name: "Int".into() });
+        let boolean = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Boolean".into(),
+        });
+
+        let names: Vec<_> = (0..4).map(|_| context.fresh_index()).collect();
+        for window in names.windows(2) {
+            context
+                .constraints
+                .push(Constraint::UnifyDeep(window[0], window[1], None))
+                .unwrap();
+        }
+
+        let mut solve = Solve::new(context);
+        solve.solve();
+
+        let substitution = &solve.context.solutions.substitution;
+        let root = substitution.find(names[0]);
+        assert!(names.iter().all(|name| substitution.find(*name) == root));
+        assert_eq!(substitution.solution(root), None);
+
+        let other = solve.context.fresh_index();
+        for constraint in [
+            Constraint::UnifySolve(names[3], int, None),
+            Constraint::UnifySolve(other, boolean, None),
+            Constraint::UnifyDeep(names[0], other, None),
+        ] {
+            solve.context.constraints.push(constraint).unwrap();
+        }
+        solve.solve();
+
+        let substitution = &solve.context.solutions.substitution;
+        assert_eq!(substitution.find(other), substitution.find(names[1]));
+        assert!(matches!(
+            solve.context.solutions.unification_errors[..],
+            [(UnifyError::CannotUnify(..), _)]
+        ));
+    }
+
+    #[test]
+    fn unification_conflicts() {
+        let context = &mut Context::default();
+
+        let [int, string, array] = ["Int", "String", "Array"].map(|name| {
+            context
+                .volatile
+                .type_arena
+                .allocate(Type::Constructor { name: name.into() })
+        });
+        let names = [(); 3].map(|_| context.fresh_index());
+        let [t, u, w] = names.map(|name| {
+            context
+                .volatile
+                .type_arena
+                .allocate(Type::Unification { name })
+        });
+        let [array_u, array_w] = [u, w].map(|argument| {
+            context.volatile.type_arena.allocate(Type::Application {
+                function: array,
+                arguments: vector![argument],
+            })
+        });
+
+        Unify::new(context).unify(t, int);
+        Unify::new(context).unify(t, string);
+        // Both solutions are emitted before either is solved, such that
+        // only the occurs check against the substitution catches the cycle.
+        Unify::new(context).unify(w, array_u);
+        Unify::new(context).unify(u, array_w);
+
+        let mut solve = Solve::new(context);
+        solve.solve();
+
+        let solutions = &solve.context.solutions;
+        assert_eq!(solutions.substitution.solution(names[0]), Some(int));
+        assert!(matches!(
+            solutions.unification_errors[..],
+            [
+                (UnifyError::InfiniteType(..), _),
+                (UnifyError::CannotUnify(t_idx, u_idx), _),
+            ] if (t_idx, u_idx) == (int, string)
+        ));
+    }
+
+    #[test]
+    fn spans_lambda_application() {
+        let context = &mut Context::default();
+
+        let span = |start, end| Span {
+            file: "Main.purs".into(),
+            start,
+            end,
+        };
+
+        let f = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "f".into() });
+        let x = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "x".into() });
+        let application = context.volatile.expr_arena.allocate(Expr::Application {
+            function: f,
+            arguments: vector![x],
+        });
+        let lambda = context.volatile.expr_arena.allocate(Expr::Lambda {
+            arguments: vector!["f".into(), "x".into()],
+            body: application,
+        });
+
+        context.volatile.expr_spans.insert(lambda, span(0, 10));
+        context.volatile.expr_spans.insert(application, span(5, 10));
+
+        let t_idx = Infer::new(context).infer(lambda).unwrap();
+
+        let Type::Function { arguments, result } = &context.volatile.type_arena[t_idx] else {
+            unreachable!();
+        };
+
+        assert_eq!(context.type_span(t_idx), Some(span(0, 10)));
+        assert_eq!(context.type_span(arguments[0]), Some(span(0, 10)));
+        assert_eq!(context.type_span(*result), Some(span(5, 10)));
+    }
+
+    #[test]
+    fn let_polymorphism() {
+        let context = &mut Context::default();
+
+        let boolean = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Boolean".into(),
+        });
+        let a = context.volatile.type_arena.allocate(Type::Variable {
+            name: "a".into(),
+            rank: 0,
+        });
+        let eq_ty = context.volatile.type_arena.allocate(Type::Function {
+            arguments: vector![a, a],
+            result: boolean,
+        });
+        let eq_ty = context.volatile.type_arena.allocate(Type::Constrained {
+            assertions: vector![Assertion {
+                name: "Eq".into(),
+                arguments: vector![a],
+            }],
+            ty: eq_ty,
+        });
+        let eq_ty = context.volatile.type_arena.allocate(Type::Forall {
+            variables: vector![TypeVariableBinder {
+                name: "a".into(),
+                kind: None,
+            }],
+            rank: 0,
+            ty: eq_ty,
+        });
+        context.environment.values.insert("eq".into(), eq_ty);
+        context.environment.classes.insert(
+            "Eq".into(),
+            Class {
+                arity: 1,
+                functional_dependencies: vector![],
+            },
+        );
+        context.environment.instances.insert("Eq".into(), vec![]);
+
+        let x = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "x".into() });
+        let y = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "y".into() });
+        let eq = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "eq".into() });
+        let eq_x_y = context.volatile.expr_arena.allocate(Expr::Application {
+            function: eq,
+            arguments: vector![x, y],
+        });
+        let value = context.volatile.expr_arena.allocate(Expr::Lambda {
+            arguments: vector!["x".into(), "y".into()],
+            body: eq_x_y,
+        });
+
+        let id = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "id".into() });
+        let id_id = context.volatile.expr_arena.allocate(Expr::Application {
+            function: id,
+            arguments: vector![id],
+        });
+        let id_value = context.volatile.expr_arena.allocate(Expr::Lambda {
+            arguments: vector!["x".into()],
+            body: x,
+        });
+
+        let same = context.volatile.expr_arena.allocate(Expr::Variable {
+            name: "same".into(),
+        });
+        let same_id = context.volatile.expr_arena.allocate(Expr::Application {
+            function: same,
+            arguments: vector![id_id, id],
+        });
+        let body = context.volatile.expr_arena.allocate(Expr::LetRec {
+            bindings: vector![("id".into(), id_value)],
+            body: same_id,
+        });
+        let expression = context.volatile.expr_arena.allocate(Expr::Let {
+            name: "same".into(),
+            value,
+            body,
+        });
+
+        let mut infer = Infer::new(context);
+        let value_ty = infer.infer(value).unwrap();
+        let t_idx = infer.infer(expression).unwrap();
+
+        let value_ty = infer.generalize(&[value_ty])[0];
+        let t_idx = infer.generalize(&[t_idx])[0];
+
+        let context = &*infer.to_solve().context;
+        assert!(context.solutions.unification_errors.is_empty());
+        assert_eq!(
+            pretty_print_ty(&context.volatile.type_arena, value_ty),
+            "forall a. Eq a => a -> a -> Boolean"
+        );
+        assert_eq!(
+            pretty_print_ty(&context.volatile.type_arena, t_idx),
+            "Boolean"
+        );
+        assert_eq!(context.solutions.entailment_deferred.len(), 1);
+    }
+
+    #[test]
+    fn literal_primitives() {
+        let context = &mut Context::default();
+
+        let float = context.volatile.expr_arena.allocate(Expr::Literal {
+            literal: Literal::Float(1.0),
+        });
+        let t_idx = Infer::new(context).infer(float).unwrap();
+        assert_eq!(
+            pretty_print_ty(&context.volatile.type_arena, t_idx),
+            "Number"
+        );
+
+        context.environment.set_primitives(Primitives {
+            integer_class: Some("FromInteger".into()),
+            ..Primitives::default()
+        });
+        context.environment.classes.insert(
+            "FromInteger".into(),
+            Class {
+                arity: 1,
+                functional_dependencies: vector![],
+            },
+        );
+        let number = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Number".into(),
+        });
+        context.environment.instances.insert(
+            "FromInteger".into(),
+            vec![Instance {
+                assertion: Assertion {
+                    name: "FromInteger".into(),
+                    arguments: vector![number],
+                },
+                dependencies: vector![],
+                span: None,
+            }],
+        );
+        let negate = context.volatile.type_arena.allocate(Type::Function {
+            arguments: vector![number],
+            result: number,
+        });
+        context.environment.values.insert("negate".into(), negate);
+
+        let one = context.volatile.expr_arena.allocate(Expr::Literal {
+            literal: Literal::Integer(1),
+        });
+        let negate = context.volatile.expr_arena.allocate(Expr::Variable {
+            name: "negate".into(),
+        });
+        let negate_one = context.volatile.expr_arena.allocate(Expr::Application {
+            function: negate,
+            arguments: vector![one],
+        });
+
+        let mut infer = Infer::new(context);
+        let one_ty = infer.infer(one).unwrap();
+        let one_ty = infer.generalize(&[one_ty])[0];
+        infer.infer(negate_one).unwrap();
+
+        let mut solve = infer.to_solve();
+        solve.solve();
+
+        assert_eq!(
+            pretty_print_ty(&solve.context.volatile.type_arena, one_ty),
+            "forall a. FromInteger a => a"
+        );
+        assert_eq!(solve.context.solutions.entailment_evidences.len(), 1);
+        assert!(solve.context.solutions.entailment_deferred.is_empty());
+    }
+
+    #[test]
+    fn case_exhaustiveness() {
+        let context = &mut Context::default();
+
+        let a = context.volatile.type_arena.allocate(Type::Variable {
+            name: "a".into(),
+            rank: 0,
+        });
+        Declare::new(context)
+            .declare_data(&DataDecl {
+                name: "Maybe".into(),
+                span: None,
+                type_params: vector![TypeVariableBinder {
+                    name: "a".into(),
+                    kind: None,
+                }],
+                constructors: vector![("Nothing".into(), vector![]), ("Just".into(), vector![a])],
+            })
+            .unwrap();
+
+        let m = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "m".into() });
+        let x = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "x".into() });
+        let just_x = Pattern::Constructor {
+            name: "Just".into(),
+            arguments: vector![Pattern::Variable { name: "x".into() }],
+        };
+        let case = context.volatile.expr_arena.allocate(Expr::Case {
+            scrutinee: m,
+            branches: vector![
+                CaseBranch {
+                    pattern: just_x.clone(),
+                    body: x,
+                },
+                CaseBranch {
+                    pattern: just_x,
+                    body: x,
+                },
+            ],
+        });
+        let expression = context.volatile.expr_arena.allocate(Expr::Lambda {
+            arguments: vector!["m".into()],
+            body: case,
+        });
+
+        let mut infer = Infer::new(context);
+        let t_idx = infer.infer(expression).unwrap();
+        let t_idx = infer.generalize(&[t_idx])[0];
+
+        assert_eq!(
+            pretty_print_ty(&context.volatile.type_arena, t_idx),
+            "forall a. Maybe a -> a"
+        );
+
+        let errors = context.pattern_errors();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], PatternError::Redundant(e_idx, 1) if e_idx == case));
+        assert!(matches!(
+            &errors[1],
+            PatternError::NonExhaustive(e_idx, missing) if *e_idx == case
+                && matches!(
+                    missing.as_slice(),
+                    [Pattern::Constructor { name, arguments }]
+                        if name == "Nothing" && arguments.is_empty()
+                )
+        ));
+
+        // Constructors registered without a data declaration are complete.
+        let unit = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Unit".into(),
+        });
+        let environment = context.environment_mut();
+        environment.insert_type("Unit".into(), Kind::Type);
+        environment.insert_constructor("Unit".into(), "Unit".into(), unit);
+
+        let case = context.volatile.expr_arena.allocate(Expr::Case {
+            scrutinee: m,
+            branches: vector![CaseBranch {
+                pattern: Pattern::Constructor {
+                    name: "Unit".into(),
+                    arguments: vector![],
+                },
+                body: m,
+            }],
+        });
+        let expression = context.volatile.expr_arena.allocate(Expr::Lambda {
+            arguments: vector!["m".into()],
+            body: case,
+        });
+
+        Infer::new(context).infer(expression).unwrap();
+        assert_eq!(context.pattern_errors().len(), 2);
+    }
+
+    #[test]
+    fn annotation_checking() {
+        let context = &mut Context::default();
+
+        let int = context
+            .volatile
+            .type_arena
+            .allocate(Type::Constructor { name: "Int".into() });
+        let boolean = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Boolean".into(),
+        });
+        let int_int = context.volatile.type_arena.allocate(Type::Function {
+            arguments: vector![int],
+            result: int,
+        });
+        let int_boolean = context.volatile.type_arena.allocate(Type::Function {
+            arguments: vector![int],
+            result: boolean,
+        });
+
+        let x = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "x".into() });
+        let identity = context.volatile.expr_arena.allocate(Expr::Lambda {
+            arguments: vector!["x".into()],
+            body: x,
+        });
+        let annotated = context.volatile.expr_arena.allocate(Expr::Annotation {
+            expr: identity,
+            ty: int_int,
+        });
+
+        let mut infer = Infer::new(context);
+        let t_idx = infer.infer(annotated).unwrap();
+        assert_eq!(t_idx, int_int);
+
+        let mut solve = infer.to_solve();
+        solve.solve();
+
+        // The argument is taken from the annotation, so nothing is left to solve.
+        assert!(solve.context.solutions.substitution.is_empty());
+        assert!(solve.context.solutions.unification_errors.is_empty());
+
+        let mut infer = Infer::new(context);
+        infer.check(identity, int_boolean).unwrap();
+
+        let mut solve = infer.to_solve();
+        solve.solve();
+
+        assert!(matches!(
+            solve.context.solutions.unification_errors.as_slice(),
+            [(UnifyError::CannotUnify(t_idx, u_idx), _)] if *t_idx == boolean && *u_idx == int
+        ));
+
+        // Types registered without a data declaration are kind checked too.
+        context
+            .environment_mut()
+            .insert_type("Maybe".into(), Kind::arrow(Kind::Type, Kind::Type));
+        let maybe = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Maybe".into(),
+        });
+        let maybe_int = context.volatile.type_arena.allocate(Type::Application {
+            function: maybe,
+            arguments: vector![int],
+        });
+        let maybe_maybe_int = context.volatile.type_arena.allocate(Type::Function {
+            arguments: vector![maybe_int],
+            result: maybe_int,
+        });
+        let annotated = context.volatile.expr_arena.allocate(Expr::Annotation {
+            expr: identity,
+            ty: maybe_maybe_int,
+        });
+
+        let mut infer = Infer::new(context);
+        assert_eq!(infer.infer(annotated).unwrap(), maybe_maybe_int);
+        assert!(context.kind_errors.is_empty());
+    }
+
+    #[test]
+    fn record_rows() {
+        let context = &mut Context::default();
+
+        let r = context
+            .volatile
+            .expr_arena
+            .allocate(Expr::Variable { name: "r".into() });
+        let r_x = context.volatile.expr_arena.allocate(Expr::Access {
+            expr: r,
+            label: "x".into(),
+        });
+        let get_x = context.volatile.expr_arena.allocate(Expr::Lambda {
+            arguments: vector!["r".into()],
+            body: r_x,
+        });
+        let one = context.volatile.expr_arena.allocate(Expr::Literal {
+            literal: Literal::Float(1.0),
+        });
+        let true_ = context.volatile.expr_arena.allocate(Expr::Literal {
+            literal: Literal::Boolean(true),
+        });
+        let record = context.volatile.expr_arena.allocate(Expr::Record {
+            fields: vector![("y".into(), one), ("x".into(), true_)],
+        });
+        let updated = context.volatile.expr_arena.allocate(Expr::Update {
+            expr: record,
+            fields: vector![("x".into(), one)],
+        });
+        let get_x_variable = context.volatile.expr_arena.allocate(Expr::Variable {
+            name: "getX".into(),
+        });
+        let get_x_updated = context.volatile.expr_arena.allocate(Expr::Application {
+            function: get_x_variable,
+            arguments: vector![updated],
+        });
+        let expression = context.volatile.expr_arena.allocate(Expr::Let {
+            name: "getX".into(),
+            value: get_x,
+            body: get_x_updated,
+        });
+
+        let mut infer = Infer::new(context);
+        let get_x_ty = infer.infer(get_x).unwrap();
+        let get_x_ty = infer.generalize(&[get_x_ty])[0];
+        let t_idx = infer.infer(expression).unwrap();
+        let t_idx = infer.generalize(&[t_idx])[0];
+
+        let mut solve = infer.to_solve();
+        solve.solve();
+
+        assert!(solve.context.solutions.unification_errors.is_empty());
+        assert_eq!(
+            pretty_print_ty(&solve.context.volatile.type_arena, get_x_ty),
+            "forall a b. { x :: a | b } -> a"
+        );
+        assert_eq!(
+            pretty_print_ty(&solve.context.volatile.type_arena, t_idx),
+            "Number"
+        );
+
+        let context = &mut Context::default();
+
+        let int = context
+            .volatile
+            .type_arena
+            .allocate(Type::Constructor { name: "Int".into() });
+        let tail = context.fresh_unification();
+        let open = context.volatile.type_arena.allocate(Type::Row {
+            labels: vector![("x".into(), int)],
+            tail: Some(tail),
+        });
+        let other = context.volatile.type_arena.allocate(Type::Row {
+            labels: vector![("y".into(), int)],
+            tail: Some(tail),
+        });
+        let duplicate = context.volatile.type_arena.allocate(Type::Row {
+            labels: vector![("x".into(), int), ("x".into(), int)],
+            tail: None,
+        });
+
+        Unify::new(context).unify(open, other);
+        Unify::new(context).unify(open, duplicate);
+
+        let mut solve = Solve::new(context);
+        solve.solve();
+
+        assert!(matches!(
+            solve.context.solutions.unification_errors.as_slice(),
+            [
+                (UnifyError::InfiniteRow(_, u_idx), _),
+                (UnifyError::DuplicateLabel(v_idx, label), _),
+            ] if *u_idx == other && *v_idx == duplicate && label == "x"
+        ));
+    }
+
+    #[test]
+    fn kind_checking() {
+        let context = &mut Context::default();
+
+        context
+            .environment_mut()
+            .insert_type("Maybe".into(), Kind::arrow(Kind::Type, Kind::Type));
+
+        let int = context
+            .volatile
+            .type_arena
+            .allocate(Type::Constructor { name: "Int".into() });
+        let maybe = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Maybe".into(),
+        });
+        let maybe_int = context.volatile.type_arena.allocate(Type::Application {
+            function: maybe,
+            arguments: vector![int],
+        });
+        let int_int = context.volatile.type_arena.allocate(Type::Application {
+            function: int,
+            arguments: vector![int],
+        });
+        let f = context.volatile.type_arena.allocate(Type::Variable {
+            name: "f".into(),
+            rank: 0,
+        });
+        let f_int = context.volatile.type_arena.allocate(Type::Application {
+            function: f,
+            arguments: vector![int],
+        });
+        let forall_f = context.volatile.type_arena.allocate(Type::Forall {
+            variables: vector![TypeVariableBinder {
+                name: "f".into(),
+                kind: None,
+            }],
+            rank: 0,
+            ty: f_int,
+        });
+
+        let mut kind_check = KindCheck::new(context);
+        assert_eq!(kind_check.infer(maybe_int).unwrap(), Kind::Type);
+        assert_eq!(
+            kind_check.infer(maybe).unwrap(),
+            Kind::arrow(Kind::Type, Kind::Type)
+        );
+        assert_eq!(kind_check.infer(forall_f).unwrap(), Kind::Type);
+        assert!(matches!(
+            kind_check.infer(int_int),
+            Err(KindError::CannotUnify(None, t_idx, Kind::Type, Kind::Arrow(..))) if t_idx == int_int
+        ));
+
+        let one = context.volatile.expr_arena.allocate(Expr::Literal {
+            literal: Literal::Integer(1),
+        });
+        let annotated = context.volatile.expr_arena.allocate(Expr::Annotation {
+            expr: one,
+            ty: int_int,
+        });
+
+        // The ill-kinded annotation is reported, and the literal is still
+        // checked against it rather than being inferred on its own.
+        let mut infer = Infer::new(context);
+        let t_idx = infer.infer(annotated).unwrap();
+        assert_eq!(t_idx, int_int);
+
+        let mut solve = infer.to_solve();
+        solve.solve();
+
+        assert!(matches!(
+            solve.context.solutions.unification_errors.as_slice(),
+            [(UnifyError::CannotUnify(..), Some(e_idx))] if *e_idx == one
+        ));
+        assert_eq!(solve.context.kind_errors().len(), 1);
+    }
+
+    #[test]
+    fn synonym_expansion() {
+        let context = &mut Context::default();
+
+        let int = context
+            .volatile
+            .type_arena
+            .allocate(Type::Constructor { name: "Int".into() });
+        let tuple = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Tuple".into(),
+        });
+        let pair = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Pair".into(),
+        });
+        let a = context.volatile.type_arena.allocate(Type::Variable {
+            name: "a".into(),
+            rank: 0,
+        });
+        let tuple_a_a = context.volatile.type_arena.allocate(Type::Application {
+            function: tuple,
+            arguments: vector![a, a],
+        });
+        Declare::new(context)
+            .declare_synonym(
+                "Pair".into(),
+                Synonym {
+                    parameters: vector!["a".into()],
+                    ty: tuple_a_a,
+                },
+            )
+            .unwrap();
+
+        let pair_int = context.volatile.type_arena.allocate(Type::Application {
+            function: pair,
+            arguments: vector![int],
+        });
+        let tuple_int_int = context.volatile.type_arena.allocate(Type::Application {
+            function: tuple,
+            arguments: vector![int, int],
+        });
+        let unification = context.fresh_unification();
+        let tuple_int_unification = context.volatile.type_arena.allocate(Type::Application {
+            function: tuple,
+            arguments: vector![int, unification],
+        });
+        let solved = context.fresh_unification();
+
+        context.environment.classes.insert(
+            "Show".into(),
+            Class {
+                arity: 1,
+                functional_dependencies: vector![],
+            },
+        );
+        context.environment.instances.insert(
+            "Show".into(),
+            vec![Instance {
+                assertion: Assertion {
+                    name: "Show".into(),
+                    arguments: vector![tuple_int_int],
+                },
+                dependencies: vector![],
+                span: None,
+            }],
+        );
+        let index = context.fresh_index();
+        context
+            .constraints
+            .push(Constraint::ClassEntail(
+                index,
+                Assertion {
+                    name: "Show".into(),
+                    arguments: vector![pair_int],
+                },
+                None,
+            ))
+            .unwrap();
+
+        Unify::new(context).unify(pair_int, tuple_int_unification);
+        Unify::new(context).unify(solved, pair_int);
+
+        let mut solve = Solve::new(context);
+        solve.solve();
+
+        assert!(solve.context.solutions.unification_errors.is_empty());
+        assert_eq!(solve.context.solutions.entailment_evidences.len(), 1);
+
+        let mut infer = Infer::new(solve.context);
+        let unification = infer.generalize(&[unification])[0];
+        assert_eq!(unification, int);
+        let solved = infer.generalize(&[solved])[0];
+        assert_eq!(
+            pretty_print_ty(&solve.context.volatile.type_arena, solved),
+            "Pair Int"
+        );
+
+        Unify::new(solve.context).unify(pair, tuple);
+        solve.solve();
+
+        assert!(matches!(
+            solve.context.solutions.unification_errors.as_slice(),
+            [(UnifyError::PartialSynonym(t_idx, 1, 0), _)] if *t_idx == pair
+        ));
+
+        // Arguments beyond the arity of a synonym extend its body.
+        let string = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "String".into(),
+        });
+        let first = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "First".into(),
+        });
+        let tuple_int = context.volatile.type_arena.allocate(Type::Application {
+            function: tuple,
+            arguments: vector![int],
+        });
+        let first_string = context.volatile.type_arena.allocate(Type::Application {
+            function: first,
+            arguments: vector![string],
+        });
+        let tuple_int_string = context.volatile.type_arena.allocate(Type::Application {
+            function: tuple,
+            arguments: vector![int, string],
+        });
+        let mut declare = Declare::new(context);
+        declare
+            .declare_synonym(
+                "First".into(),
+                Synonym {
+                    parameters: vector![],
+                    ty: tuple_int,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            Expand::new(context).expand(first_string),
+            Ok(tuple_int_string)
+        );
+
+        // Synonyms that refer to themselves, whether directly or through
+        // another synonym, would never stop expanding.
+        let left = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Left".into(),
+        });
+        let right = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Right".into(),
+        });
+        let tuple_int_left = context.volatile.type_arena.allocate(Type::Application {
+            function: tuple,
+            arguments: vector![int, left],
+        });
+        let mut declare = Declare::new(context);
+        declare
+            .declare_synonym(
+                "Left".into(),
+                Synonym {
+                    parameters: vector![],
+                    ty: right,
+                },
+            )
+            .unwrap();
+        for ty in [right, tuple_int_left] {
+            assert!(matches!(
+                declare.declare_synonym(
+                    "Right".into(),
+                    Synonym {
+                        parameters: vector![],
+                        ty,
+                    },
+                ),
+                Err(KindError::CyclicSynonym(None, t_idx, _)) if t_idx == ty
+            ));
+        }
+    }
+
+    #[test]
+    fn data_declarations() {
+        let context = &mut Context::default();
+
+        let a = context.volatile.type_arena.allocate(Type::Variable {
+            name: "a".into(),
+            rank: 0,
+        });
+        let f = context.volatile.type_arena.allocate(Type::Variable {
+            name: "f".into(),
+            rank: 0,
+        });
+        let f_a = context.volatile.type_arena.allocate(Type::Application {
+            function: f,
+            arguments: vector![a],
+        });
+        let list = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "List".into(),
+        });
+        let list_a = context.volatile.type_arena.allocate(Type::Application {
+            function: list,
+            arguments: vector![a],
+        });
+        let binder = |name: &str| TypeVariableBinder {
+            name: name.into(),
+            kind: None,
+        };
+
+        let mut declare = Declare::new(context);
+        declare
+            .declare_data(&DataDecl {
+                name: "List".into(),
+                span: None,
+                type_params: vector![binder("a")],
+                constructors: vector![
+                    ("Nil".into(), vector![]),
+                    ("Cons".into(), vector![a, list_a])
+                ],
+            })
+            .unwrap();
+        declare
+            .declare_data(&DataDecl {
+                name: "Compose".into(),
+                span: None,
+                type_params: vector![binder("f"), binder("a"), binder("b")],
+                constructors: vector![("Compose".into(), vector![f_a])],
+            })
+            .unwrap();
+        assert!(matches!(
+            declare.declare_data(&DataDecl {
+                name: "Bad".into(),
+                span: None,
+                type_params: vector![binder("a")],
+                constructors: vector![("Bad".into(), vector![list])],
+            }),
+            Err(KindError::CannotUnify(..))
+        ));
+
+        let environment = &context.environment;
+        assert_eq!(
+            environment.kinds["List"],
+            Kind::arrow(Kind::Type, Kind::Type)
+        );
+        assert_eq!(
+            environment.kinds["Compose"],
+            Kind::arrow(
+                Kind::arrow(Kind::Type, Kind::Type),
+                Kind::arrow(Kind::Type, Kind::arrow(Kind::Type, Kind::Type))
+            )
+        );
+        assert_eq!(environment.data["List"], ["Nil", "Cons"]);
+        assert_eq!(environment.constructor_types["Cons"], "List");
+        assert_eq!(
+            pretty_print_ty(
+                &context.volatile.type_arena,
+                environment.constructors["Cons"]
+            ),
+            "forall (a :: Type). a -> List a -> List a"
+        );
+
+        Declare::new(context)
+            .declare_data(&DataDecl {
+                name: "List".into(),
+                span: None,
+                type_params: vector![binder("a")],
+                constructors: vector![("Empty".into(), vector![])],
+            })
+            .unwrap();
+        let environment = &context.environment;
+        assert_eq!(environment.data["List"], ["Empty"]);
+        assert!(!environment.constructors.contains_key("Cons"));
+        assert!(!environment.constructor_types.contains_key("Nil"));
+    }
+
+    #[test]
+    fn module_binding_groups() {
+        let context = &mut Context::default();
+
+        let number = context.volatile.type_arena.allocate(Type::Constructor {
+            name: "Number".into(),
+        });
+        let number_number = context.volatile.type_arena.allocate(Type::Function {
+            arguments: vector![number],
+            result: number,
+        });
+
+        let mut variable = |name: &str| {
+            context
+                .volatile
+                .expr_arena
+                .allocate(Expr::Variable { name: name.into() })
+        };
+        let (x, f, g, k) = (variable("x"), variable("f"), variable("g"), variable("k"));
+
+        let arena = &mut context.volatile.expr_arena;
+        let one = arena.allocate(Expr::Literal {
+            literal: Literal::Float(1.0),
+        });
+        let f_one = arena.allocate(Expr::Application {
+            function: f,
+            arguments: vector![one],
+        });
+        let h_value = arena.allocate(Expr::Application {
+            function: k,
+            arguments: vector![f_one],
+        });
+        let g_x = arena.allocate(Expr::Application {
+            function: g,
+            arguments: vector![x],
+        });
+        let f_value = arena.allocate(Expr::Lambda {
+            arguments: vector!["x".into()],
+            body: g_x,
+        });
+        let f_x = arena.allocate(Expr::Application {
+            function: f,
+            arguments: vector![x],
+        });
+        let g_value = arena.allocate(Expr::Lambda {
+            arguments: vector!["x".into()],
+            body: f_x,
+        });
+        let k_value = arena.allocate(Expr::Lambda {
+            arguments: vector!["x".into()],
+            body: x,
+        });
+
+        let declaration = |name: &str, signature, value| ValueDecl {
+            name: name.into(),
+            signature,
+            signature_span: None,
+            value,
+        };
+        let module = Module {
+            values: vector![
+                declaration("h", None, h_value),
+                declaration("f", None, f_value),
+                declaration("g", None, g_value),
+                declaration("k", Some(number_number), k_value),
+            ],
+            ..Module::default()
+        };
+
+        let mut infer = Infer::new(context);
+        infer.infer_module(&module).unwrap();
+
+        let mut solve = infer.to_solve();
+        solve.solve();
+
+        assert!(solve.context.solutions.unification_errors.is_empty());
+
+        let values = &solve.context.environment.values;
+        let arena = &solve.context.volatile.type_arena;
+        assert_eq!(pretty_print_ty(arena, values["h"]), "Number");
+        assert_eq!(pretty_print_ty(arena, values["f"]), "forall a b. a -> b");
+        assert_eq!(pretty_print_ty(arena, values["g"]), "forall a b. a -> b");
+        assert_eq!(values["k"], number_number);
+    }
+
+    #[test]
+    fn class_declarations() {
+        let mut context = Context::default();
+
+        let source = "
+class Append xs ys zs | xs ys -> zs
+
+instance Append Nil ys ys
+
+instance Append xs ys zs => Append (Cons x xs) ys (Cons x zs)
+";
+        let volatile = &mut context.volatile;
+        let (module, errors) = parse_module(
+            "Append.iwc",
+            source,
+            Arenas {
+                expr_arena: &mut volatile.expr_arena,
+                type_arena: &volatile.type_arena,
+                expr_spans: &volatile.expr_spans,
+                type_spans: &volatile.type_spans,
+            },
+        );
+        assert!(errors.is_empty());
+
+        let mut declare = Declare::new(&mut context);
+        for class in &module.classes {
+            declare.declare_class(class);
+        }
+        for instance in &module.instances {
+            declare.declare_instance(instance).unwrap();
+        }
+
+        let dependencies = &context.environment.classes["Append"].functional_dependencies;
+        assert_eq!(dependencies[0].domain, vector![0, 1]);
+        assert_eq!(dependencies[0].codomain, vector![2]);
+
+        let instances = &context.environment.instances["Append"];
+        assert_eq!(
+            pretty_print_assertion(&context.volatile.type_arena, &instances[1].assertion),
+            "Append (Cons x xs) ys (Cons x zs)"
+        );
+
+        let mut arguments: Vector<_> = ["Cons One (Cons Zero Nil)", "Cons One Nil"]
+            .into_iter()
+            .map(|source| {
+                let volatile = &mut context.volatile;
+                parse_type(
+                    "<type>",
+                    source,
+                    Arenas {
+                        expr_arena: &mut volatile.expr_arena,
+                        type_arena: &volatile.type_arena,
+                        expr_spans: &volatile.expr_spans,
+                        type_spans: &volatile.type_spans,
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+        let u = context.fresh_unification();
+        arguments.push_back(u);
+
+        let index = context.fresh_index();
+        context
+            .constraints
+            .push(Constraint::ClassEntail(
+                index,
+                Assertion {
+                    name: "Append".into(),
+                    arguments,
+                },
+                None,
+            ))
+            .unwrap();
+
+        let mut infer = Infer::new(&mut context);
+        let solution = infer.generalize(&[u])[0];
+        assert_eq!(
+            pretty_print_ty(&context.volatile.type_arena, solution),
+            "Cons One (Cons Zero (Cons One Nil))"
+        );
+    }
+
+    #[test]
+    fn zonking() {
+        let mut context = Context::default();
+
+        let source = "
+data Maybe a = Nothing | Just a
+
+value = \\f -> let x = f 1 in case x of { Just y -> y; Nothing -> false }
+";
+        let (expr_arena, type_arena, expr_spans, type_spans) = context.arenas_mut();
+        let (module, errors) = parse_module(
+            "Main.iwc",
+            source,
+            Arenas {
+                expr_arena,
+                type_arena,
+                expr_spans,
+                type_spans,
+            },
+        );
+        assert!(errors.is_empty());
+        Declare::new(&mut context)
+            .declare_data(&module.data[0])
+            .unwrap();
+        let e_idx = module.values[0].value;
+
+        let mut infer = Infer::new(&mut context);
+        let t_idx = infer.infer(e_idx).unwrap();
+        let mut solve = infer.to_solve();
+        solve.solve();
+
+        let context = &*solve.context;
+        let arena = context.type_arena();
+        assert_eq!(
+            TypePrinter::new(arena, PrettyOptions::default())
+                .print_ty(Zonk::new(context).zonk(t_idx)),
+            "(Int -> Maybe Boolean) -> Boolean"
+        );
+        assert_eq!(
+            pretty_print_expr_with_types(
+                context.expr_arena(),
+                e_idx,
+                arena,
+                &Zonk::new(context).zonk_expr_types()
+            ),
+            "(\\f -> (let x = ((f :: Int -> Maybe Boolean) (1 :: Int) :: Maybe Boolean) in \
+             (case (x :: Maybe Boolean) of { Just y -> (y :: Boolean); Nothing -> (false :: Boolean) } \
+             :: Boolean) :: Boolean) :: (Int -> Maybe Boolean) -> Boolean)"
+        );
+    }
+
+    #[test]
+    fn generalization() {
+        let mut context = Context::default();
+
+        let source = "
+class Eq a
+
+instance Eq Int
+
+equals :: Eq a => a -> a -> Boolean
+equals x y = true
+
+same x y = equals x y
+
+value = \\x y -> equals x x
+";
+        let (expr_arena, type_arena, expr_spans, type_spans) = context.arenas_mut();
+        let (module, errors) = parse_module(
+            "Main.iwc",
+            source,
+            Arenas {
+                expr_arena,
+                type_arena,
+                expr_spans,
+                type_spans,
+            },
+        );
+        assert!(errors.is_empty());
+        let mut declare = Declare::new(&mut context);
+        declare.declare_class(&module.classes[0]);
+        declare.declare_instance(&module.instances[0]).unwrap();
+
+        let (values, value) = module.values.clone().split_at(2);
+        let mut infer = Infer::new(&mut context);
+        infer.infer_module(&Module { values, ..module }).unwrap();
+
+        let t_idx = infer.infer(value[0].value).unwrap();
+        let t_idx = infer.generalize(&[t_idx])[0];
+
+        let mut printer = TypePrinter::new(context.type_arena(), PrettyOptions::default());
+        assert_eq!(
+            printer.print_ty(context.environment().value("same").unwrap()),
+            "forall a. Eq a => a -> a -> Boolean"
+        );
+        assert_eq!(
+            printer.print_ty(t_idx),
+            "forall a b. Eq a => a -> b -> Boolean"
+        );
+        assert!(context.solutions().entailment_deferred().is_empty());
+    }
 }
@@ -3,58 +3,105 @@ use std::{
     iter::zip,
 };
 
-use iwc_core_ast::ty::{Assertion, TypeIdx};
+use iwc_core_ast::{
+    expr::ExprIdx,
+    ty::{Assertion, Type, TypeIdx},
+};
 use iwc_core_constraint::Constraint;
 use iwc_core_error::UnifyError;
 
 use crate::{
     context::Context,
     entail::{Entail, EntailResult, Evidence},
+    substitution::Substitution,
     unify::Unify,
 };
 
 pub struct Solve<'context> {
     pub(crate) context: &'context mut Context,
-    pub(crate) unification_solved: HashMap<usize, TypeIdx>,
-    pub(crate) unification_deferred: Vec<(usize, usize)>,
-    pub(crate) unification_errors: Vec<UnifyError>,
+}
+
+// NOTE: Solutions are kept in the `Context` rather than in `Solve`, such
+// that inference can solve constraints in the middle of an expression,
+// like for let-generalization, without losing what has been solved so far.
+#[derive(Debug, Default)]
+pub struct Solutions {
+    pub(crate) substitution: Substitution,
+    pub(crate) unification_errors: Vec<(UnifyError, Option<ExprIdx>)>,
     pub(crate) entailment_evidences: HashMap<usize, Evidence>,
     pub(crate) entailment_deferred: Vec<DeferredAssertion>,
 }
 
 #[derive(Debug)]
 pub struct DeferredAssertion {
-    index: usize,
-    assertion: Assertion,
-    needs_solution: HashSet<(usize, usize)>,
+    pub(crate) index: usize,
+    pub(crate) assertion: Assertion,
+    pub(crate) needs_solution: HashSet<(usize, usize)>,
+    pub(crate) origin: Option<ExprIdx>,
+}
+
+impl Solutions {
+    pub fn substitution(&self) -> &Substitution {
+        &self.substitution
+    }
+
+    /// Unification errors along with the expressions they arose from.
+    pub fn unification_errors(&self) -> &[(UnifyError, Option<ExprIdx>)] {
+        &self.unification_errors
+    }
+
+    pub fn entailment_evidences(&self) -> &HashMap<usize, Evidence> {
+        &self.entailment_evidences
+    }
+
+    /// Assertions that are left over once solving is done either match no
+    /// instance or are ambiguous.
+    pub fn entailment_deferred(&self) -> &[DeferredAssertion] {
+        &self.entailment_deferred
+    }
+}
+
+impl DeferredAssertion {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn assertion(&self) -> &Assertion {
+        &self.assertion
+    }
+
+    pub fn origin(&self) -> Option<ExprIdx> {
+        self.origin
+    }
 }
 
 impl<'context> Solve<'context> {
     pub fn new(context: &'context mut Context) -> Self {
-        Self {
-            context,
-            unification_solved: HashMap::new(),
-            unification_deferred: Vec::new(),
-            unification_errors: Vec::new(),
-            entailment_evidences: HashMap::new(),
-            entailment_deferred: Vec::new(),
-        }
+        Self { context }
     }
 
     pub(crate) fn step(&mut self) {
         while let Ok(constraint) = self.context.constraints.pop() {
+            if let Some(recorded) = &mut self.context.recorded {
+                recorded.push(constraint.clone());
+            }
             match constraint {
-                Constraint::ClassEntail(index, assertion) => {
+                Constraint::ClassEntail(index, assertion, origin) => {
                     match Entail::new(self.context).entail(&assertion) {
                         EntailResult::Solved {
                             evidence,
                             instance_assertion,
                         } => {
-                            self.entailment_evidences.insert(index, evidence);
+                            self.context
+                                .solutions
+                                .entailment_evidences
+                                .insert(index, evidence);
                             for (t_idx, u_idx) in
                                 zip(&assertion.arguments, &instance_assertion.arguments)
                             {
-                                Unify::new(self.context).unify(*t_idx, *u_idx);
+                                Unify::new(self.context)
+                                    .with_origin(origin)
+                                    .unify(*t_idx, *u_idx);
                             }
                         }
                         EntailResult::Depends {
@@ -62,11 +109,16 @@ impl<'context> Solve<'context> {
                             instance_assertion,
                             instance_dependencies,
                         } => {
-                            self.entailment_evidences.insert(index, evidence);
+                            self.context
+                                .solutions
+                                .entailment_evidences
+                                .insert(index, evidence);
                             for (t_idx, u_idx) in
                                 zip(&assertion.arguments, &instance_assertion.arguments)
                             {
-                                Unify::new(self.context).unify(*t_idx, *u_idx);
+                                Unify::new(self.context)
+                                    .with_origin(origin)
+                                    .unify(*t_idx, *u_idx);
                             }
                             for (dependency_index, dependency_assertion) in instance_dependencies {
                                 self.context
@@ -74,90 +126,168 @@ impl<'context> Solve<'context> {
                                     .push(Constraint::ClassEntail(
                                         dependency_index,
                                         dependency_assertion,
+                                        origin,
                                     ))
                                     .unwrap();
                             }
                         }
                         EntailResult::Deferred { needs_solution } => {
-                            self.entailment_deferred.push(DeferredAssertion {
-                                index,
-                                assertion,
-                                needs_solution,
-                            });
+                            self.context
+                                .solutions
+                                .entailment_deferred
+                                .push(DeferredAssertion {
+                                    index,
+                                    assertion,
+                                    needs_solution,
+                                    origin,
+                                });
                         }
                     }
                 }
-                Constraint::UnifyDeep(t_name, u_name) => {
-                    let t_idx = self.unification_solved.get(&t_name).copied();
-                    let u_idx = self.unification_solved.get(&u_name).copied();
-                    match (t_idx, u_idx) {
-                        (Some(t_idx), Some(u_idx)) => {
-                            Unify::new(self.context).unify(t_idx, u_idx);
-                        }
-                        (None, Some(u_idx)) => {
-                            self.unification_solved.insert(t_name, u_idx);
-                        }
-                        (Some(t_idx), None) => {
-                            self.unification_solved.insert(u_name, t_idx);
-                        }
-                        (None, None) => {
-                            // Avoids infinite loops with unsolvable unifications.
-                            self.unification_deferred.push((t_name, u_name));
-                        }
-                    }
+                Constraint::UnifyDeep(t_name, u_name, origin) => {
+                    self.unify_deep(t_name, u_name, origin)
                 }
-                Constraint::UnifySolve(t_name, u_idx) => {
-                    self.unification_solved.insert(t_name, u_idx);
+                Constraint::UnifySolve(t_name, u_idx, origin) => {
+                    self.unify_solve(t_name, u_idx, origin)
                 }
-                Constraint::UnifyError(error) => {
-                    self.unification_errors.push(error);
+                Constraint::UnifyError(error, origin) => {
+                    self.context
+                        .solutions
+                        .unification_errors
+                        .push((error, origin));
                 }
             }
         }
 
-        self.unification_deferred.retain(|(t_name, u_name)| {
-            let t_idx = self.unification_solved.get(t_name).copied();
-            let u_idx = self.unification_solved.get(u_name).copied();
-            if t_idx.is_some() || u_idx.is_some() {
-                self.context
-                    .constraints
-                    .push(Constraint::UnifyDeep(*t_name, *u_name))
-                    .unwrap();
-                false
-            } else {
-                true
-            }
-        });
-
-        let entailment_deferred = std::mem::take(&mut self.entailment_deferred);
+        let entailment_deferred = std::mem::take(&mut self.context.solutions.entailment_deferred);
         for DeferredAssertion {
             index,
             mut assertion,
             mut needs_solution,
+            origin,
         } in entailment_deferred
         {
+            // Assertions that didn't match any instance have nothing that
+            // needs solving, and stay deferred until they're reported.
+            let was_stuck = needs_solution.is_empty();
+
+            // Variables that were merged into another are revisited with
+            // the representative, which may have been solved in the meantime.
+            let substitution = &mut self.context.solutions.substitution;
+            let type_arena = &self.context.volatile.type_arena;
             needs_solution.retain(|(index, name)| {
-                if let Some(value) = self.unification_solved.get(name) {
-                    assertion.arguments[*index] = *value;
+                let root = substitution.find_compress(*name);
+                if let Some(solution) = substitution.solution(root) {
+                    assertion.arguments[*index] = solution;
+                    false
+                } else if root != *name {
+                    assertion.arguments[*index] =
+                        type_arena.allocate(Type::Unification { name: root });
                     false
                 } else {
                     true
                 }
             });
 
-            if needs_solution.is_empty() {
+            if needs_solution.is_empty() && !was_stuck {
                 self.context
                     .constraints
-                    .push(Constraint::ClassEntail(index, assertion))
+                    .push(Constraint::ClassEntail(index, assertion, origin))
                     .unwrap();
             } else {
-                self.entailment_deferred.push(DeferredAssertion {
-                    index,
-                    assertion,
-                    needs_solution,
-                });
+                self.context
+                    .solutions
+                    .entailment_deferred
+                    .push(DeferredAssertion {
+                        index,
+                        assertion,
+                        needs_solution,
+                        origin,
+                    });
+            }
+        }
+    }
+
+    // NOTE: Unifications are emitted concurrently and can be stale by the
+    // time that they're solved, so the occurs check is repeated against the
+    // current substitution to keep solutions free of cycles.
+    fn unify_deep(&mut self, t_name: usize, u_name: usize, origin: Option<ExprIdx>) {
+        let substitution = &mut self.context.solutions.substitution;
+        let t_root = substitution.find_compress(t_name);
+        let u_root = substitution.find_compress(u_name);
+        if t_root == u_root {
+            return;
+        }
+        let occurs = match (substitution.solution(t_root), substitution.solution(u_root)) {
+            (Some(t_idx), None) => Some((u_name, t_idx)),
+            (None, Some(u_idx)) => Some((t_name, u_idx)),
+            _ => None,
+        };
+        if let Some((name, t_idx)) = occurs {
+            if Unify::new(self.context).occurs_check(t_idx, name) {
+                let error = UnifyError::InfiniteType(name, t_idx);
+                return self
+                    .context
+                    .solutions
+                    .unification_errors
+                    .push((error, origin));
             }
         }
+        if let Some((t_idx, u_idx)) = self.context.solutions.substitution.union(t_name, u_name) {
+            Unify::new(self.context)
+                .with_origin(origin)
+                .unify(t_idx, u_idx);
+        }
+    }
+
+    /// A variable that is already solved has its solutions unified instead,
+    /// such that conflicting solutions are reported rather than replaced.
+    fn unify_solve(&mut self, t_name: usize, u_idx: TypeIdx, origin: Option<ExprIdx>) {
+        let t_root = self.context.solutions.substitution.find_compress(t_name);
+        if let Some(t_idx) = self.context.solutions.substitution.solution(t_root) {
+            return Unify::new(self.context)
+                .with_origin(origin)
+                .unify(t_idx, u_idx);
+        }
+        if Unify::new(self.context).occurs_check(u_idx, t_name) {
+            let error = UnifyError::InfiniteType(t_name, u_idx);
+            return self
+                .context
+                .solutions
+                .unification_errors
+                .push((error, origin));
+        }
+        self.context.solutions.substitution.solve(t_name, u_idx);
+    }
+
+    /// Assertions that are deferred but equal to a given once zonked, like
+    /// ones whose arguments were solved after they were last attempted, are
+    /// solved by that given.
+    pub(crate) fn discharge_givens(&mut self) {
+        let entailment_deferred = std::mem::take(&mut self.context.solutions.entailment_deferred);
+        for deferred in entailment_deferred {
+            match Entail::new(self.context).given(&deferred.assertion) {
+                Some(index) => {
+                    self.context
+                        .solutions
+                        .entailment_evidences
+                        .insert(deferred.index, Evidence::Given { index });
+                }
+                None => self.context.solutions.entailment_deferred.push(deferred),
+            }
+        }
+    }
+
+    /// Solves an assertion along with everything it gives rise to, returning
+    /// the index that its evidence is recorded under.
+    pub fn solve_assertion(&mut self, assertion: Assertion) -> usize {
+        let index = self.context.fresh_index();
+        self.context
+            .constraints
+            .push(Constraint::ClassEntail(index, assertion, None))
+            .unwrap();
+        self.solve();
+        index
     }
 
     pub fn solve(&mut self) {
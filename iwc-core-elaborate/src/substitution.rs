@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use iwc_core_ast::ty::TypeIdx;
+
+/// A union-find over unification variables, where the variables that were
+/// unified with each other form an equivalence class whose representative
+/// holds the solution for all of them.
+///
+/// Variables that were never merged or solved aren't stored at all.
+#[derive(Debug, Default)]
+pub struct Substitution {
+    parents: HashMap<usize, usize>,
+    ranks: HashMap<usize, usize>,
+    solutions: HashMap<usize, TypeIdx>,
+}
+
+impl Substitution {
+    /// Finds the representative of `name` without compressing its path, for
+    /// lookups through shared references such as zonking and the occurs
+    /// check. The solver compresses the paths it looks up on its own with
+    /// `find_compress`, and union by rank keeps the rest logarithmic.
+    pub fn find(&self, mut name: usize) -> usize {
+        while let Some(parent) = self.parents.get(&name) {
+            name = *parent;
+        }
+        name
+    }
+
+    pub fn solution(&self, name: usize) -> Option<TypeIdx> {
+        self.solutions.get(&self.find(name)).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty() && self.solutions.is_empty()
+    }
+
+    /// The unification variables that were either solved or merged into
+    /// another variable, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = usize> + '_ {
+        self.parents.keys().chain(self.solutions.keys()).copied()
+    }
+
+    pub(crate) fn find_compress(&mut self, name: usize) -> usize {
+        let root = self.find(name);
+        let mut name = name;
+        while name != root {
+            let parent = self.parents[&name];
+            self.parents.insert(name, root);
+            name = parent;
+        }
+        root
+    }
+
+    /// Merges the classes of `t_name` and `u_name`, returning both of their
+    /// solutions if they had one, as those still have to be unified.
+    pub(crate) fn union(&mut self, t_name: usize, u_name: usize) -> Option<(TypeIdx, TypeIdx)> {
+        let t_root = self.find_compress(t_name);
+        let u_root = self.find_compress(u_name);
+        if t_root == u_root {
+            return None;
+        }
+
+        let t_rank = self.ranks.get(&t_root).copied().unwrap_or(0);
+        let u_rank = self.ranks.get(&u_root).copied().unwrap_or(0);
+        let (root, child) = if t_rank < u_rank {
+            (u_root, t_root)
+        } else {
+            (t_root, u_root)
+        };
+        if t_rank == u_rank {
+            self.ranks.insert(root, t_rank + 1);
+        }
+        self.ranks.remove(&child);
+        self.parents.insert(child, root);
+
+        match (self.solutions.remove(&child), self.solutions.get(&root)) {
+            (Some(child_solution), Some(root_solution)) => Some((child_solution, *root_solution)),
+            (Some(child_solution), None) => {
+                self.solutions.insert(root, child_solution);
+                None
+            }
+            (None, _) => None,
+        }
+    }
+
+    /// Solves the class of `name`, which is expected to be unsolved.
+    pub(crate) fn solve(&mut self, name: usize, t_idx: TypeIdx) {
+        let root = self.find_compress(name);
+        self.solutions.insert(root, t_idx);
+    }
+}
@@ -1,21 +1,55 @@
 use std::iter::zip;
 
-use iwc_core_ast::ty::{Assertion, Type, TypeIdx};
+use im::Vector;
+use iwc_arena::InternedArena;
+use iwc_core_ast::{
+    expr::ExprIdx,
+    ty::{Assertion, Type, TypeIdx},
+};
 use iwc_core_constraint::Constraint;
 use iwc_core_error::UnifyError;
 
-use crate::context::Context;
+use smol_str::SmolStr;
+
+use crate::{context::Context, expand::Expand};
 
 pub struct Unify<'context> {
-    context: &'context mut Context,
+    context: &'context Context,
+    origin: Option<ExprIdx>,
 }
 
 impl<'context> Unify<'context> {
-    pub fn new(context: &'context mut Context) -> Self {
-        Self { context }
+    pub fn new(context: &'context Context) -> Self {
+        Self {
+            context,
+            origin: None,
+        }
+    }
+
+    /// Attributes the constraints emitted from here on to `origin`.
+    pub fn with_origin(mut self, origin: Option<ExprIdx>) -> Self {
+        self.origin = origin;
+        self
     }
 
     pub fn unify(&mut self, t_idx: TypeIdx, u_idx: TypeIdx) {
+        let t_idx = self.resolve(t_idx);
+        let u_idx = self.resolve(u_idx);
+        // Types are interned, so equal indices imply equal types.
+        if t_idx == u_idx {
+            return;
+        }
+        // NOTE: Synonyms are not expanded when solving unification variables,
+        // such that solutions keep the synonym names where possible.
+        if !self.is_unification(t_idx) && !self.is_unification(u_idx) {
+            let (Some(t_expanded), Some(u_expanded)) = (self.expand(t_idx), self.expand(u_idx))
+            else {
+                return;
+            };
+            if (t_expanded, u_expanded) != (t_idx, u_idx) {
+                return self.unify(t_expanded, u_expanded);
+            }
+        }
         match (
             &self.context.volatile.type_arena[t_idx],
             &self.context.volatile.type_arena[u_idx],
@@ -71,25 +105,20 @@ impl<'context> Unify<'context> {
                     result: u_result,
                 },
             ) => {
-                if t_arguments.len() != u_arguments.len() {
-                    return self.emit_error(UnifyError::InvalidArity(
-                        t_idx,
-                        t_arguments.len(),
-                        u_idx,
-                        u_arguments.len(),
-                    ));
-                }
-
                 let t_arguments = t_arguments.clone();
                 let u_arguments = u_arguments.clone();
 
                 let t_result = *t_result;
                 let u_result = *u_result;
 
-                for (t_argument, u_argument) in zip(t_arguments, u_arguments) {
-                    self.unify(t_argument, u_argument);
+                let count = t_arguments.len().min(u_arguments.len());
+                for (t_argument, u_argument) in zip(&t_arguments, &u_arguments) {
+                    self.unify(*t_argument, *u_argument);
                 }
 
+                let type_arena = &self.context.volatile.type_arena;
+                let t_result = applied(type_arena, &t_arguments, count, t_result);
+                let u_result = applied(type_arena, &u_arguments, count, u_result);
                 self.unify(t_result, u_result);
             }
             // Application
@@ -114,17 +143,33 @@ impl<'context> Unify<'context> {
                     self.unify(t_argument, u_argument);
                 }
             }
+            // Row
+            (Type::Row { .. }, Type::Row { .. }) => self.unify_rows(t_idx, u_idx),
+            // Record
+            (Type::Record { row: t_row }, Type::Record { row: u_row }) => {
+                let t_row = *t_row;
+                let u_row = *u_row;
+                self.unify(t_row, u_row);
+            }
             (_, _) => {
                 self.emit_error(UnifyError::CannotUnify(t_idx, u_idx));
             }
         }
     }
 
-    fn occurs_check(&self, t_idx: TypeIdx, u_name: usize) -> bool {
+    /// Whether `u_name` occurs in `t_idx`, looking through the solutions of
+    /// the unification variables in it.
+    pub(crate) fn occurs_check(&self, t_idx: TypeIdx, u_name: usize) -> bool {
         match &self.context.volatile.type_arena[t_idx] {
             Type::Constructor { .. } => false,
             Type::Variable { .. } => false,
-            Type::Unification { name: t_name } => *t_name == u_name,
+            Type::Unification { name: t_name } => {
+                let substitution = &self.context.solutions.substitution;
+                substitution.find(*t_name) == substitution.find(u_name)
+                    || substitution
+                        .solution(*t_name)
+                        .is_some_and(|solution| self.occurs_check(solution, u_name))
+            }
             Type::Function { arguments, result } => {
                 arguments
                     .iter()
@@ -148,27 +193,179 @@ impl<'context> Unify<'context> {
                         .any(|argument| self.occurs_check(*argument, u_name))
                 }) || self.occurs_check(*ty, u_name)
             }
+            Type::Row { labels, tail } => {
+                labels
+                    .iter()
+                    .any(|(_, label)| self.occurs_check(*label, u_name))
+                    || tail.is_some_and(|tail| self.occurs_check(tail, u_name))
+            }
+            Type::Record { row } => self.occurs_check(*row, u_name),
+        }
+    }
+
+    /// Unifies rows modulo the order of their labels. Labels present on
+    /// only one side are pushed into the tail of the other, sharing a fresh
+    /// tail when both rows are open.
+    fn unify_rows(&mut self, t_idx: TypeIdx, u_idx: TypeIdx) {
+        let (t_labels, t_tail) = self.flatten_row(t_idx);
+        let (u_labels, u_tail) = self.flatten_row(u_idx);
+
+        for (row_idx, labels) in [(t_idx, &t_labels), (u_idx, &u_labels)] {
+            if let Some(label) = duplicate_label(labels) {
+                return self.emit_error(UnifyError::DuplicateLabel(row_idx, label));
+            }
+        }
+
+        let mut t_rest = Vector::new();
+        for (label, t_label) in &t_labels {
+            match u_labels.iter().find(|(u_label, _)| u_label == label) {
+                Some((_, u_label)) => self.unify(*t_label, *u_label),
+                None => t_rest.push_back((label.clone(), *t_label)),
+            }
+        }
+        let u_rest: Vector<_> = u_labels
+            .into_iter()
+            .filter(|(label, _)| !t_labels.iter().any(|(t_label, _)| t_label == label))
+            .collect();
+
+        match (t_rest.is_empty(), u_rest.is_empty(), t_tail, u_tail) {
+            (true, true, Some(t_tail), Some(u_tail)) => self.unify(t_tail, u_tail),
+            (true, true, Some(tail), None) | (true, true, None, Some(tail)) => {
+                let empty = self.context.volatile.type_arena.allocate(Type::Row {
+                    labels: Vector::new(),
+                    tail: None,
+                });
+                self.unify(tail, empty);
+            }
+            (true, true, None, None) => (),
+            (true, false, Some(t_tail), _) => self.unify_tail(t_tail, u_rest, u_tail),
+            (false, true, _, Some(u_tail)) => self.unify_tail(u_tail, t_rest, t_tail),
+            (false, false, Some(t_tail), Some(u_tail)) => {
+                if t_tail == u_tail {
+                    if let Type::Unification { name } = &self.context.volatile.type_arena[t_tail] {
+                        return self.emit_error(UnifyError::InfiniteRow(*name, u_idx));
+                    }
+                }
+                let tail = self.context.fresh_unification();
+                self.unify_tail(t_tail, u_rest, Some(tail));
+                self.unify_tail(u_tail, t_rest, Some(tail));
+            }
+            _ => self.emit_error(UnifyError::CannotUnify(t_idx, u_idx)),
+        }
+    }
+
+    fn unify_tail(
+        &mut self,
+        tail: TypeIdx,
+        labels: Vector<(SmolStr, TypeIdx)>,
+        rest: Option<TypeIdx>,
+    ) {
+        let row = self
+            .context
+            .volatile
+            .type_arena
+            .allocate(Type::Row { labels, tail: rest });
+        if let Type::Unification { name } = &self.context.volatile.type_arena[tail] {
+            if self.occurs_check(row, *name) {
+                return self.emit_error(UnifyError::InfiniteRow(*name, row));
+            }
+        }
+        self.unify(tail, row);
+    }
+
+    /// Collects the labels of a row, following tails that are rows themselves.
+    fn flatten_row(&self, mut t_idx: TypeIdx) -> (Vec<(SmolStr, TypeIdx)>, Option<TypeIdx>) {
+        let mut labels = vec![];
+        loop {
+            match &self.context.volatile.type_arena[t_idx] {
+                Type::Row {
+                    labels: row_labels,
+                    tail,
+                } => {
+                    labels.extend(row_labels.iter().cloned());
+                    match tail {
+                        Some(tail) => t_idx = self.resolve(*tail),
+                        None => return (labels, None),
+                    }
+                }
+                _ => return (labels, Some(t_idx)),
+            }
+        }
+    }
+
+    /// Follows solved unification variables to their solutions, such that
+    /// a variable is never solved twice without the solutions being unified.
+    fn resolve(&self, mut t_idx: TypeIdx) -> TypeIdx {
+        while let Type::Unification { name } = &self.context.volatile.type_arena[t_idx] {
+            match self.context.solutions.substitution.solution(*name) {
+                Some(solution) => t_idx = solution,
+                None => break,
+            }
+        }
+        t_idx
+    }
+
+    fn is_unification(&self, t_idx: TypeIdx) -> bool {
+        matches!(
+            self.context.volatile.type_arena[t_idx],
+            Type::Unification { .. }
+        )
+    }
+
+    fn expand(&mut self, t_idx: TypeIdx) -> Option<TypeIdx> {
+        match Expand::new(self.context).expand(t_idx) {
+            Ok(t_idx) => Some(t_idx),
+            Err((arity, given)) => {
+                self.emit_error(UnifyError::PartialSynonym(t_idx, arity, given));
+                None
+            }
         }
     }
 
     fn emit_deep(&mut self, t_name: usize, u_name: usize) {
         self.context
             .constraints
-            .push(Constraint::UnifyDeep(t_name, u_name))
+            .push(Constraint::UnifyDeep(t_name, u_name, self.origin))
             .unwrap();
     }
 
     fn emit_solve(&mut self, t_name: usize, u_idx: TypeIdx) {
         self.context
             .constraints
-            .push(Constraint::UnifySolve(t_name, u_idx))
+            .push(Constraint::UnifySolve(t_name, u_idx, self.origin))
             .unwrap();
     }
 
     fn emit_error(&mut self, error: UnifyError) {
         self.context
             .constraints
-            .push(Constraint::UnifyError(error))
+            .push(Constraint::UnifyError(error, self.origin))
             .unwrap();
     }
 }
+
+/// The function that's left once the first `count` of its `arguments` are
+/// applied, or just its `result` if none are left. Functions take all of
+/// their arguments at once, so this is how one is matched against another
+/// that takes fewer, as in `a -> b -> c` and `a -> (b -> c)`.
+pub(crate) fn applied(
+    type_arena: &InternedArena<Type>,
+    arguments: &Vector<TypeIdx>,
+    count: usize,
+    result: TypeIdx,
+) -> TypeIdx {
+    let arguments = arguments.skip(count);
+    if arguments.is_empty() {
+        return result;
+    }
+    type_arena.allocate(Type::Function { arguments, result })
+}
+
+fn duplicate_label(labels: &[(SmolStr, TypeIdx)]) -> Option<SmolStr> {
+    labels.iter().enumerate().find_map(|(index, (label, _))| {
+        labels[..index]
+            .iter()
+            .any(|(previous, _)| previous == label)
+            .then(|| label.clone())
+    })
+}
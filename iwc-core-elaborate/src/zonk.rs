@@ -0,0 +1,72 @@
+use iwc_arena::{ArenaMap, InternedArena};
+use iwc_core_ast::{
+    expr::ExprIdx,
+    span::Spans,
+    ty::{
+        traversal::{default_traverse_ty, Traversal},
+        Assertion, Type, TypeIdx,
+    },
+};
+
+use crate::context::Context;
+
+/// Applies the solutions to unification variables, following a variable
+/// through as many solutions as it takes to reach a type that isn't one.
+/// Unification variables without a solution are replaced by the
+/// representative of the variables that they were unified with.
+pub struct Zonk<'context> {
+    context: &'context Context,
+}
+
+impl<'context> Zonk<'context> {
+    pub fn new(context: &'context Context) -> Self {
+        Self { context }
+    }
+
+    pub fn zonk(&mut self, t_idx: TypeIdx) -> TypeIdx {
+        self.traverse_ty(t_idx)
+    }
+
+    pub fn zonk_assertion(&mut self, assertion: &Assertion) -> Assertion {
+        self.traverse_assertion(assertion)
+    }
+
+    /// Zonks the types recorded for every expression that was inferred or
+    /// checked, such as for `pretty_print_expr_with_types`.
+    pub fn zonk_expr_types(&mut self) -> ArenaMap<ExprIdx, TypeIdx> {
+        let mut expr_types = ArenaMap::new();
+        for (e_idx, t_idx) in self.context.expr_types.iter() {
+            expr_types.insert(e_idx, self.zonk(*t_idx));
+        }
+        expr_types
+    }
+}
+
+impl<'context> Traversal for Zonk<'context> {
+    fn arena(&self) -> &InternedArena<Type> {
+        &self.context.volatile.type_arena
+    }
+
+    fn spans(&self) -> &Spans<Type> {
+        &self.context.volatile.type_spans
+    }
+
+    fn traverse_ty(&mut self, ty_idx: TypeIdx) -> TypeIdx {
+        match &self.context.volatile.type_arena[ty_idx] {
+            Type::Unification { name } => {
+                let substitution = &self.context.solutions.substitution;
+                let root = substitution.find(*name);
+                match substitution.solution(root) {
+                    Some(solution) => self.traverse_ty(solution),
+                    None if root != *name => self
+                        .context
+                        .volatile
+                        .type_arena
+                        .allocate(Type::Unification { name: root }),
+                    None => ty_idx,
+                }
+            }
+            _ => default_traverse_ty(self, ty_idx),
+        }
+    }
+}
@@ -1,9 +1,96 @@
-use iwc_core_ast::ty::TypeIdx;
+use std::fmt;
 
-#[derive(Debug)]
+use iwc_core_ast::{
+    expr::{ExprIdx, Pattern},
+    kind::Kind,
+    span::Span,
+    ty::TypeIdx,
+};
+use smol_str::SmolStr;
+
+#[derive(Debug, Clone)]
 pub enum UnifyError {
     CannotUnify(TypeIdx, TypeIdx),
     ImpredicativeType(usize, TypeIdx),
     InfiniteType(usize, TypeIdx),
-    InvalidArity(TypeIdx, usize, TypeIdx, usize),
+    DuplicateLabel(TypeIdx, SmolStr),
+    InfiniteRow(usize, TypeIdx),
+    PartialSynonym(TypeIdx, usize, usize),
+}
+
+#[derive(Debug)]
+pub enum PatternError {
+    NonExhaustive(ExprIdx, Vec<Pattern>),
+    Redundant(ExprIdx, usize),
 }
+
+/// Kind errors carry the span of the signature, annotation or declaration
+/// that was being checked, as types are interned and a type on its own
+/// doesn't tell which of its occurrences is at fault.
+#[derive(Debug)]
+pub enum KindError {
+    CannotUnify(Option<Span>, TypeIdx, Kind, Kind),
+    InfiniteKind(Option<Span>, TypeIdx, usize, Kind),
+    UnknownConstructor(Option<Span>, TypeIdx, SmolStr),
+    PartialSynonym(Option<Span>, TypeIdx, usize, usize),
+    CyclicSynonym(Option<Span>, TypeIdx, SmolStr),
+    UnknownClass(Option<Span>, SmolStr),
+    ClassArity(Option<Span>, SmolStr, usize, usize),
+}
+
+impl KindError {
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            KindError::CannotUnify(span, ..)
+            | KindError::InfiniteKind(span, ..)
+            | KindError::UnknownConstructor(span, ..)
+            | KindError::PartialSynonym(span, ..)
+            | KindError::CyclicSynonym(span, ..)
+            | KindError::UnknownClass(span, ..)
+            | KindError::ClassArity(span, ..) => span.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    UnexpectedCharacter(Span, char),
+    UnterminatedLiteral(Span),
+    InvalidLiteral(Span),
+    UnexpectedToken(Span, SmolStr, SmolStr),
+    MissingBinding(Span, SmolStr),
+    DuplicateSignature(Span, SmolStr),
+    DuplicateBinding(Span, SmolStr),
+    UnknownParameter(Span, SmolStr),
+}
+
+impl ParseError {
+    pub fn span(&self) -> &Span {
+        match self {
+            ParseError::UnexpectedCharacter(span, _)
+            | ParseError::UnterminatedLiteral(span)
+            | ParseError::InvalidLiteral(span)
+            | ParseError::UnexpectedToken(span, _, _)
+            | ParseError::MissingBinding(span, _)
+            | ParseError::DuplicateSignature(span, _)
+            | ParseError::DuplicateBinding(span, _)
+            | ParseError::UnknownParameter(span, _) => span,
+        }
+    }
+}
+
+/// An error that stops inference altogether, such as an unbound variable,
+/// which is reported at the innermost expression that was being inferred.
+#[derive(Debug)]
+pub struct InferError {
+    pub origin: ExprIdx,
+    pub message: String,
+}
+
+impl fmt::Display for InferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for InferError {}
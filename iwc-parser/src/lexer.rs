@@ -0,0 +1,311 @@
+use iwc_core_ast::span::Span;
+use iwc_core_error::ParseError;
+use smol_str::SmolStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    Lower(SmolStr),
+    Upper(SmolStr),
+    Integer(i64),
+    Float(f64),
+    String(SmolStr),
+    Char(char),
+    True,
+    False,
+    Let,
+    Rec,
+    In,
+    Case,
+    Of,
+    Forall,
+    Data,
+    Class,
+    Instance,
+    Backslash,
+    Arrow,
+    FatArrow,
+    DoubleColon,
+    Colon,
+    Equals,
+    Pipe,
+    Comma,
+    Semicolon,
+    Dot,
+    Underscore,
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    Eof,
+}
+
+impl TokenKind {
+    pub(crate) fn describe(&self) -> SmolStr {
+        match self {
+            TokenKind::Lower(name) | TokenKind::Upper(name) => format!("`{}`", name).into(),
+            TokenKind::Integer(value) => format!("`{}`", value).into(),
+            TokenKind::Float(value) => format!("`{:?}`", value).into(),
+            TokenKind::String(value) => format!("{:?}", value).into(),
+            TokenKind::Char(value) => format!("{:?}", value).into(),
+            TokenKind::True => "`true`".into(),
+            TokenKind::False => "`false`".into(),
+            TokenKind::Let => "`let`".into(),
+            TokenKind::Rec => "`rec`".into(),
+            TokenKind::In => "`in`".into(),
+            TokenKind::Case => "`case`".into(),
+            TokenKind::Of => "`of`".into(),
+            TokenKind::Forall => "`forall`".into(),
+            TokenKind::Data => "`data`".into(),
+            TokenKind::Class => "`class`".into(),
+            TokenKind::Instance => "`instance`".into(),
+            TokenKind::Backslash => "`\\`".into(),
+            TokenKind::Arrow => "`->`".into(),
+            TokenKind::FatArrow => "`=>`".into(),
+            TokenKind::DoubleColon => "`::`".into(),
+            TokenKind::Colon => "`:`".into(),
+            TokenKind::Equals => "`=`".into(),
+            TokenKind::Pipe => "`|`".into(),
+            TokenKind::Comma => "`,`".into(),
+            TokenKind::Semicolon => "`;`".into(),
+            TokenKind::Dot => "`.`".into(),
+            TokenKind::Underscore => "`_`".into(),
+            TokenKind::LeftParen => "`(`".into(),
+            TokenKind::RightParen => "`)`".into(),
+            TokenKind::LeftBrace => "`{`".into(),
+            TokenKind::RightBrace => "`}`".into(),
+            TokenKind::Eof => "the end of the declaration".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// Whether the token starts a line with no indentation, which is where
+    /// top-level declarations begin.
+    pub(crate) declaration: bool,
+}
+
+pub(crate) struct Lexer<'source> {
+    file: SmolStr,
+    source: &'source str,
+    offset: usize,
+    errors: Vec<ParseError>,
+}
+
+impl<'source> Lexer<'source> {
+    pub(crate) fn new(file: SmolStr, source: &'source str) -> Self {
+        Self {
+            file,
+            source,
+            offset: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Characters that can't be lexed are reported and skipped.
+    pub(crate) fn lex(mut self) -> (Vec<Token>, Vec<ParseError>) {
+        let mut tokens = vec![];
+        loop {
+            let declaration = self.skip_trivia();
+            let start = self.offset;
+            let Some(character) = self.peek() else {
+                tokens.push(Token {
+                    kind: TokenKind::Eof,
+                    start,
+                    end: start,
+                    declaration: true,
+                });
+                return (tokens, self.errors);
+            };
+            if let Some(kind) = self.token(character) {
+                tokens.push(Token {
+                    kind,
+                    start,
+                    end: self.offset,
+                    declaration,
+                });
+            }
+        }
+    }
+
+    fn token(&mut self, character: char) -> Option<TokenKind> {
+        let start = self.offset;
+        self.bump();
+        let kind = match character {
+            '\\' | 'λ' => TokenKind::Backslash,
+            '→' => TokenKind::Arrow,
+            '⇒' => TokenKind::FatArrow,
+            '∀' => TokenKind::Forall,
+            '|' => TokenKind::Pipe,
+            ',' => TokenKind::Comma,
+            ';' => TokenKind::Semicolon,
+            '.' => TokenKind::Dot,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '{' => TokenKind::LeftBrace,
+            '}' => TokenKind::RightBrace,
+            ':' if self.eat(':') => TokenKind::DoubleColon,
+            ':' => TokenKind::Colon,
+            '=' if self.eat('>') => TokenKind::FatArrow,
+            '=' => TokenKind::Equals,
+            '-' if self.eat('>') => TokenKind::Arrow,
+            '-' if self.peek().is_some_and(|c| c.is_ascii_digit()) => return self.number(start),
+            '"' => return self.string(start),
+            '\'' => return self.char(start),
+            c if c.is_ascii_digit() => return self.number(start),
+            c if c.is_alphabetic() || c == '_' => {
+                self.eat_while(|c| c.is_alphanumeric() || c == '_' || c == '\'');
+                return Some(self.word(&self.source[start..self.offset]));
+            }
+            c => {
+                let span = self.span(start);
+                self.errors.push(ParseError::UnexpectedCharacter(span, c));
+                return None;
+            }
+        };
+        Some(kind)
+    }
+
+    fn word(&self, word: &str) -> TokenKind {
+        match word {
+            "_" => TokenKind::Underscore,
+            "true" => TokenKind::True,
+            "false" => TokenKind::False,
+            "let" => TokenKind::Let,
+            "rec" => TokenKind::Rec,
+            "in" => TokenKind::In,
+            "case" => TokenKind::Case,
+            "of" => TokenKind::Of,
+            "forall" => TokenKind::Forall,
+            "data" => TokenKind::Data,
+            "class" => TokenKind::Class,
+            "instance" => TokenKind::Instance,
+            _ if word.starts_with(char::is_uppercase) => TokenKind::Upper(word.into()),
+            _ => TokenKind::Lower(word.into()),
+        }
+    }
+
+    fn number(&mut self, start: usize) -> Option<TokenKind> {
+        self.eat_while(|c| c.is_ascii_digit());
+        let is_float = self.peek() == Some('.')
+            && self.source[self.offset + 1..].starts_with(|c: char| c.is_ascii_digit());
+        if is_float {
+            self.bump();
+            self.eat_while(|c| c.is_ascii_digit());
+        }
+        let text = &self.source[start..self.offset];
+        let kind = if is_float {
+            text.parse().ok().map(TokenKind::Float)
+        } else {
+            text.parse().ok().map(TokenKind::Integer)
+        };
+        if kind.is_none() {
+            let span = self.span(start);
+            self.errors.push(ParseError::InvalidLiteral(span));
+        }
+        kind
+    }
+
+    fn string(&mut self, start: usize) -> Option<TokenKind> {
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    return Some(TokenKind::String(value.into()));
+                }
+                Some('\n') | None => {
+                    let span = self.span(start);
+                    self.errors.push(ParseError::UnterminatedLiteral(span));
+                    return None;
+                }
+                Some(_) => value.push(self.escaped(start)?),
+            }
+        }
+    }
+
+    fn char(&mut self, start: usize) -> Option<TokenKind> {
+        let value = match self.peek() {
+            Some('\'') | Some('\n') | None => None,
+            Some(_) => Some(self.escaped(start)?),
+        };
+        match value {
+            Some(value) if self.eat('\'') => Some(TokenKind::Char(value)),
+            _ => {
+                let span = self.span(start);
+                self.errors.push(ParseError::UnterminatedLiteral(span));
+                None
+            }
+        }
+    }
+
+    fn escaped(&mut self, start: usize) -> Option<char> {
+        let character = self.bump()?;
+        if character != '\\' {
+            return Some(character);
+        }
+        let escaped = match self.bump() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '"' | '\'')) => c,
+            _ => {
+                let span = self.span(start);
+                self.errors.push(ParseError::InvalidLiteral(span));
+                return None;
+            }
+        };
+        Some(escaped)
+    }
+
+    /// Skips whitespace and line comments, returning whether the next token
+    /// is at the very start of a line.
+    fn skip_trivia(&mut self) -> bool {
+        loop {
+            self.eat_while(|c| c.is_whitespace());
+            if self.source[self.offset..].starts_with("--") {
+                self.eat_while(|c| c != '\n');
+            } else {
+                break;
+            }
+        }
+        self.offset == 0 || self.source[..self.offset].ends_with('\n')
+    }
+
+    fn span(&self, start: usize) -> Span {
+        Span {
+            file: self.file.clone(),
+            start,
+            end: self.offset,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.offset += character.len_utf8();
+        Some(character)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&predicate) {
+            self.bump();
+        }
+    }
+}
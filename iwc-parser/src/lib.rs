@@ -0,0 +1,1121 @@
+mod lexer;
+
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use im::{vector, Vector};
+use iwc_arena::{Arena, InternedArena};
+use iwc_core_ast::{
+    decl::{ClassDecl, DataDecl, Module, ValueDecl},
+    expr::{CaseBranch, Expr, ExprIdx, Literal, Pattern},
+    kind::Kind,
+    span::{Span, Spans},
+    ty::{Assertion, FunctionalDependency, Instance, Type, TypeIdx, TypeVariableBinder},
+};
+use iwc_core_error::ParseError;
+use smol_str::SmolStr;
+
+use crate::lexer::{Lexer, Token, TokenKind};
+
+/// The arenas that parsed expressions and types are allocated into.
+pub struct Arenas<'a> {
+    pub expr_arena: &'a mut Arena<Expr>,
+    pub type_arena: &'a InternedArena<Type>,
+    pub expr_spans: &'a Spans<Expr>,
+    pub type_spans: &'a Spans<Type>,
+}
+
+/// Parses a module, where each declaration starts on a line of its own with
+/// no indentation. Declarations that fail to parse are skipped, such that
+/// the rest of the module is still parsed.
+pub fn parse_module(file: &str, source: &str, arenas: Arenas) -> (Module, Vec<ParseError>) {
+    let (tokens, errors) = Lexer::new(file.into(), source).lex();
+    let mut parser = Parser::new(file.into(), tokens, errors, arenas);
+
+    let mut module = Module::default();
+    let mut signatures = HashMap::new();
+    let mut bindings = HashSet::new();
+    for declaration in parser.declarations() {
+        parser.tokens = declaration;
+        parser.index = 0;
+        match parser.declaration() {
+            Ok(Declaration::Data(data)) => module.data.push_back(data),
+            Ok(Declaration::Class(class)) => module.classes.push_back(class),
+            Ok(Declaration::Instance(instance)) => module.instances.push_back(instance),
+            Ok(Declaration::Signature(name, span, ty)) => match signatures.entry(name) {
+                Entry::Occupied(entry) => {
+                    let name = entry.key().clone();
+                    parser
+                        .errors
+                        .push(ParseError::DuplicateSignature(span, name));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert((span, ty));
+                }
+            },
+            Ok(Declaration::Value(span, value)) => {
+                if bindings.insert(value.name.clone()) {
+                    module.values.push_back(value);
+                } else {
+                    parser
+                        .errors
+                        .push(ParseError::DuplicateBinding(span, value.name));
+                }
+            }
+            Err(error) => parser.errors.push(error),
+        }
+    }
+
+    for value in module.values.iter_mut() {
+        if let Some((span, signature)) = signatures.remove(&value.name) {
+            value.signature = Some(signature);
+            value.signature_span = Some(span);
+        }
+    }
+    let mut missing: Vec<_> = signatures.into_iter().collect();
+    missing.sort_by_key(|(_, (span, _))| span.start);
+    for (name, (span, _)) in missing {
+        parser.errors.push(ParseError::MissingBinding(span, name));
+    }
+
+    (module, parser.errors)
+}
+
+pub fn parse_expr(file: &str, source: &str, arenas: Arenas) -> Result<ExprIdx, Vec<ParseError>> {
+    parse_with(file, source, arenas, |parser| parser.expr())
+}
+
+/// Parses a type, where free type variables are implicitly quantified.
+pub fn parse_type(file: &str, source: &str, arenas: Arenas) -> Result<TypeIdx, Vec<ParseError>> {
+    parse_with(file, source, arenas, |parser| parser.scheme())
+}
+
+/// Parses a type, where free type variables are left unquantified.
+pub fn parse_open_type(
+    file: &str,
+    source: &str,
+    arenas: Arenas,
+) -> Result<TypeIdx, Vec<ParseError>> {
+    parse_with(file, source, arenas, |parser| parser.ty())
+}
+
+/// Parses an assertion such as `Eq (Maybe a)`, where type variables are
+/// left unquantified.
+pub fn parse_assertion(
+    file: &str,
+    source: &str,
+    arenas: Arenas,
+) -> Result<Assertion, Vec<ParseError>> {
+    parse_with(file, source, arenas, |parser| parser.constraint())
+}
+
+fn parse_with<T>(
+    file: &str,
+    source: &str,
+    arenas: Arenas,
+    action: impl FnOnce(&mut Parser) -> Result<T, ParseError>,
+) -> Result<T, Vec<ParseError>> {
+    let (tokens, errors) = Lexer::new(file.into(), source).lex();
+    let mut parser = Parser::new(file.into(), tokens, errors, arenas);
+    let result = action(&mut parser).and_then(|result| {
+        parser.expect(TokenKind::Eof)?;
+        Ok(result)
+    });
+    match result {
+        Ok(result) if parser.errors.is_empty() => Ok(result),
+        Ok(_) => Err(parser.errors),
+        Err(error) => {
+            parser.errors.push(error);
+            Err(parser.errors)
+        }
+    }
+}
+
+enum Declaration {
+    Data(DataDecl),
+    Class(ClassDecl),
+    Instance(Instance),
+    Signature(SmolStr, Span, TypeIdx),
+    Value(Span, ValueDecl),
+}
+
+struct Parser<'a> {
+    file: SmolStr,
+    tokens: Vec<Token>,
+    index: usize,
+    arenas: Arenas<'a>,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(file: SmolStr, tokens: Vec<Token>, errors: Vec<ParseError>, arenas: Arenas<'a>) -> Self {
+        Self {
+            file,
+            tokens,
+            index: 0,
+            arenas,
+            errors,
+        }
+    }
+
+    /// Splits the tokens at the start of each declaration, each ending with
+    /// its own end-of-file token.
+    fn declarations(&self) -> Vec<Vec<Token>> {
+        let mut declarations: Vec<Vec<Token>> = vec![];
+        for token in &self.tokens {
+            if token.declaration {
+                if let Some(declaration) = declarations.last_mut() {
+                    declaration.push(Token {
+                        kind: TokenKind::Eof,
+                        start: token.start,
+                        end: token.start,
+                        declaration: true,
+                    });
+                }
+                if token.kind != TokenKind::Eof {
+                    declarations.push(vec![]);
+                }
+            }
+            if let Some(declaration) = declarations.last_mut() {
+                if token.kind != TokenKind::Eof {
+                    declaration.push(token.clone());
+                }
+            }
+        }
+        declarations
+    }
+
+    fn declaration(&mut self) -> Result<Declaration, ParseError> {
+        let start = self.start();
+        let declaration = match self.peek() {
+            TokenKind::Data => Declaration::Data(self.data()?),
+            TokenKind::Class => Declaration::Class(self.class()?),
+            TokenKind::Instance => Declaration::Instance(self.instance()?),
+            TokenKind::Lower(name) => {
+                let name = name.clone();
+                self.bump();
+                if self.eat(TokenKind::DoubleColon) {
+                    let ty = self.scheme()?;
+                    Declaration::Signature(name, self.span(start), ty)
+                } else {
+                    let arguments = self.lowers();
+                    self.expect(TokenKind::Equals)?;
+                    let mut value = self.expr()?;
+                    if !arguments.is_empty() {
+                        value = self.allocate_expr(
+                            start,
+                            Expr::Lambda {
+                                arguments,
+                                body: value,
+                            },
+                        );
+                    }
+                    Declaration::Value(
+                        self.span(start),
+                        ValueDecl {
+                            name,
+                            signature: None,
+                            signature_span: None,
+                            value,
+                        },
+                    )
+                }
+            }
+            _ => return Err(self.unexpected("a declaration")),
+        };
+        self.expect(TokenKind::Eof)?;
+        Ok(declaration)
+    }
+
+    fn data(&mut self) -> Result<DataDecl, ParseError> {
+        let start = self.start();
+        self.expect(TokenKind::Data)?;
+        let name = self.upper()?;
+        let type_params = self.binders()?;
+        self.expect(TokenKind::Equals)?;
+
+        let mut constructors = Vector::new();
+        loop {
+            let name = self.upper()?;
+            let mut fields = Vector::new();
+            while self.starts_atype() {
+                fields.push_back(self.atype()?);
+            }
+            constructors.push_back((name, fields));
+            if !self.eat(TokenKind::Pipe) {
+                break;
+            }
+        }
+
+        Ok(DataDecl {
+            name,
+            span: Some(self.span(start)),
+            type_params,
+            constructors,
+        })
+    }
+
+    fn class(&mut self) -> Result<ClassDecl, ParseError> {
+        self.expect(TokenKind::Class)?;
+        let name = self.upper()?;
+        let parameters = self.lowers();
+
+        let mut functional_dependencies = Vector::new();
+        if self.eat(TokenKind::Pipe) {
+            loop {
+                let domain = self.parameters(&parameters)?;
+                self.expect(TokenKind::Arrow)?;
+                let codomain = self.parameters(&parameters)?;
+                functional_dependencies.push_back(FunctionalDependency { domain, codomain });
+                if !self.eat(TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+
+        Ok(ClassDecl {
+            name,
+            parameters,
+            functional_dependencies,
+        })
+    }
+
+    /// Parses the parameters on one side of a functional dependency into
+    /// their positions in the class head.
+    fn parameters(&mut self, parameters: &Vector<SmolStr>) -> Result<Vector<usize>, ParseError> {
+        let mut positions = Vector::new();
+        while let TokenKind::Lower(name) = self.peek().clone() {
+            let start = self.start();
+            self.bump();
+            match parameters.index_of(&name) {
+                Some(position) => positions.push_back(position),
+                None => return Err(ParseError::UnknownParameter(self.span(start), name)),
+            }
+        }
+        if positions.is_empty() {
+            return Err(self.unexpected("a class parameter"));
+        }
+        Ok(positions)
+    }
+
+    /// Parses an instance such as `instance Eq a => Eq (Array a)`, where
+    /// the type variables are left unquantified as instances are matched
+    /// against assertions by their type variables of rank 0.
+    fn instance(&mut self) -> Result<Instance, ParseError> {
+        let start = self.start();
+        self.expect(TokenKind::Instance)?;
+
+        let (index, errors) = (self.index, self.errors.len());
+        if let Ok(dependencies) = self.constraints() {
+            if self.eat(TokenKind::FatArrow) {
+                let assertion = self.constraint()?;
+                return Ok(Instance {
+                    assertion,
+                    dependencies,
+                    span: Some(self.span(start)),
+                });
+            }
+        }
+        self.index = index;
+        self.errors.truncate(errors);
+
+        let assertion = self.constraint()?;
+        Ok(Instance {
+            assertion,
+            dependencies: Vector::new(),
+            span: Some(self.span(start)),
+        })
+    }
+
+    fn expr(&mut self) -> Result<ExprIdx, ParseError> {
+        let start = self.start();
+        match self.peek() {
+            TokenKind::Backslash => {
+                self.bump();
+                let arguments = self.lowers();
+                if arguments.is_empty() {
+                    return Err(self.unexpected("an argument"));
+                }
+                self.expect(TokenKind::Arrow)?;
+                let body = self.expr()?;
+                Ok(self.allocate_expr(start, Expr::Lambda { arguments, body }))
+            }
+            TokenKind::Let => {
+                self.bump();
+                if self.eat(TokenKind::Rec) {
+                    let mut bindings = vector![self.binding()?];
+                    while self.eat(TokenKind::Semicolon) {
+                        bindings.push_back(self.binding()?);
+                    }
+                    self.expect(TokenKind::In)?;
+                    let body = self.expr()?;
+                    Ok(self.allocate_expr(start, Expr::LetRec { bindings, body }))
+                } else {
+                    let (name, value) = self.binding()?;
+                    self.expect(TokenKind::In)?;
+                    let body = self.expr()?;
+                    Ok(self.allocate_expr(start, Expr::Let { name, value, body }))
+                }
+            }
+            TokenKind::Case => {
+                self.bump();
+                let scrutinee = self.expr()?;
+                self.expect(TokenKind::Of)?;
+                self.expect(TokenKind::LeftBrace)?;
+                let mut branches = Vector::new();
+                loop {
+                    let pattern = self.pattern()?;
+                    self.expect(TokenKind::Arrow)?;
+                    let body = self.expr()?;
+                    branches.push_back(CaseBranch { pattern, body });
+                    if !self.eat(TokenKind::Semicolon) {
+                        break;
+                    }
+                }
+                self.expect(TokenKind::RightBrace)?;
+                Ok(self.allocate_expr(
+                    start,
+                    Expr::Case {
+                        scrutinee,
+                        branches,
+                    },
+                ))
+            }
+            _ => {
+                let expr = self.application()?;
+                if self.eat(TokenKind::DoubleColon) {
+                    let ty = self.scheme()?;
+                    Ok(self.allocate_expr(start, Expr::Annotation { expr, ty }))
+                } else {
+                    Ok(expr)
+                }
+            }
+        }
+    }
+
+    fn binding(&mut self) -> Result<(SmolStr, ExprIdx), ParseError> {
+        let start = self.start();
+        let name = self.lower()?;
+        let arguments = self.lowers();
+        self.expect(TokenKind::Equals)?;
+        let mut value = self.expr()?;
+        if !arguments.is_empty() {
+            value = self.allocate_expr(
+                start,
+                Expr::Lambda {
+                    arguments,
+                    body: value,
+                },
+            );
+        }
+        Ok((name, value))
+    }
+
+    fn application(&mut self) -> Result<ExprIdx, ParseError> {
+        let start = self.start();
+        let function = self.postfix()?;
+        let mut arguments = Vector::new();
+        while self.starts_atom() {
+            arguments.push_back(self.postfix()?);
+        }
+        if arguments.is_empty() {
+            Ok(function)
+        } else {
+            Ok(self.allocate_expr(
+                start,
+                Expr::Application {
+                    function,
+                    arguments,
+                },
+            ))
+        }
+    }
+
+    /// Parses field accesses like `r.x` and record updates like `r { x = 1 }`.
+    fn postfix(&mut self) -> Result<ExprIdx, ParseError> {
+        let start = self.start();
+        let mut expr = self.atom()?;
+        loop {
+            if self.peek() == &TokenKind::Dot {
+                self.bump();
+                let label = self.lower()?;
+                expr = self.allocate_expr(start, Expr::Access { expr, label });
+            } else if self.peek() == &TokenKind::LeftBrace
+                && matches!(self.peek_nth(1), TokenKind::Lower(_))
+                && self.peek_nth(2) == &TokenKind::Equals
+            {
+                let fields = self.fields(TokenKind::Equals)?;
+                expr = self.allocate_expr(start, Expr::Update { expr, fields });
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Result<ExprIdx, ParseError> {
+        let start = self.start();
+        let expr = match self.peek().clone() {
+            TokenKind::Lower(name) => Expr::Variable { name },
+            TokenKind::Upper(name) => Expr::Constructor { name },
+            TokenKind::LeftParen => {
+                self.bump();
+                let expr = self.expr()?;
+                self.expect(TokenKind::RightParen)?;
+                return Ok(expr);
+            }
+            TokenKind::LeftBrace => {
+                let fields = self.fields(TokenKind::Colon)?;
+                return Ok(self.allocate_expr(start, Expr::Record { fields }));
+            }
+            kind => match literal(&kind) {
+                Some(literal) => Expr::Literal { literal },
+                None => return Err(self.unexpected("an expression")),
+            },
+        };
+        self.bump();
+        Ok(self.allocate_expr(start, expr))
+    }
+
+    fn fields(&mut self, separator: TokenKind) -> Result<Vector<(SmolStr, ExprIdx)>, ParseError> {
+        self.expect(TokenKind::LeftBrace)?;
+        let mut fields = Vector::new();
+        if self.eat(TokenKind::RightBrace) {
+            return Ok(fields);
+        }
+        loop {
+            let label = self.lower()?;
+            self.expect(separator.clone())?;
+            fields.push_back((label, self.expr()?));
+            if !self.eat(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(TokenKind::RightBrace)?;
+        Ok(fields)
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        if let TokenKind::Upper(name) = self.peek().clone() {
+            self.bump();
+            let mut arguments = Vector::new();
+            while self.starts_pattern_atom() {
+                arguments.push_back(self.pattern_atom()?);
+            }
+            return Ok(Pattern::Constructor { name, arguments });
+        }
+        self.pattern_atom()
+    }
+
+    fn pattern_atom(&mut self) -> Result<Pattern, ParseError> {
+        let pattern = match self.peek().clone() {
+            TokenKind::Underscore => Pattern::Wildcard,
+            TokenKind::Lower(name) => Pattern::Variable { name },
+            TokenKind::Upper(name) => Pattern::Constructor {
+                name,
+                arguments: Vector::new(),
+            },
+            TokenKind::LeftParen => {
+                self.bump();
+                let pattern = self.pattern()?;
+                self.expect(TokenKind::RightParen)?;
+                return Ok(pattern);
+            }
+            kind => match literal(&kind) {
+                Some(literal) => Pattern::Literal { literal },
+                None => return Err(self.unexpected("a pattern")),
+            },
+        };
+        self.bump();
+        Ok(pattern)
+    }
+
+    /// Parses a type where the free type variables are quantified, as in
+    /// signatures and annotations.
+    fn scheme(&mut self) -> Result<TypeIdx, ParseError> {
+        let start = self.start();
+        let ty = self.ty()?;
+
+        let mut free = vec![];
+        self.free_variables(ty, &mut vec![], &mut free);
+        if free.is_empty() {
+            return Ok(ty);
+        }
+
+        let variables = free
+            .into_iter()
+            .map(|name| TypeVariableBinder { name, kind: None })
+            .collect();
+        Ok(self.allocate_ty(
+            start,
+            Type::Forall {
+                variables,
+                rank: 0,
+                ty,
+            },
+        ))
+    }
+
+    fn ty(&mut self) -> Result<TypeIdx, ParseError> {
+        let start = self.start();
+        if self.eat(TokenKind::Forall) {
+            let variables = self.binders()?;
+            if variables.is_empty() {
+                return Err(self.unexpected("a type variable"));
+            }
+            self.expect(TokenKind::Dot)?;
+            let ty = self.ty()?;
+            return Ok(self.allocate_ty(
+                start,
+                Type::Forall {
+                    variables,
+                    rank: 0,
+                    ty,
+                },
+            ));
+        }
+
+        // NOTE: A constraint can't be told apart from a type until the `=>`
+        // after it, so it's parsed speculatively and backtracked from.
+        let (index, errors) = (self.index, self.errors.len());
+        if let Ok(assertions) = self.constraints() {
+            if self.eat(TokenKind::FatArrow) {
+                let ty = self.ty()?;
+                return Ok(self.allocate_ty(start, Type::Constrained { assertions, ty }));
+            }
+        }
+        self.index = index;
+        self.errors.truncate(errors);
+
+        self.function()
+    }
+
+    fn constraints(&mut self) -> Result<Vector<Assertion>, ParseError> {
+        if self.eat(TokenKind::LeftParen) {
+            let mut assertions = vector![self.constraint()?];
+            while self.eat(TokenKind::Comma) {
+                assertions.push_back(self.constraint()?);
+            }
+            self.expect(TokenKind::RightParen)?;
+            Ok(assertions)
+        } else {
+            Ok(vector![self.constraint()?])
+        }
+    }
+
+    fn constraint(&mut self) -> Result<Assertion, ParseError> {
+        let name = self.upper()?;
+        let mut arguments = Vector::new();
+        while self.starts_atype() {
+            arguments.push_back(self.atype()?);
+        }
+        Ok(Assertion { name, arguments })
+    }
+
+    /// Arrows are right-associative, and a chain of them is a single
+    /// function type taking every argument before the last arrow.
+    fn function(&mut self) -> Result<TypeIdx, ParseError> {
+        let start = self.start();
+        let mut arguments = Vector::new();
+        let mut result = self.btype()?;
+        while self.eat(TokenKind::Arrow) {
+            arguments.push_back(result);
+            if self.peek() == &TokenKind::Forall {
+                result = self.ty()?;
+                break;
+            }
+            result = self.btype()?;
+        }
+        if arguments.is_empty() {
+            Ok(result)
+        } else {
+            Ok(self.allocate_ty(start, Type::Function { arguments, result }))
+        }
+    }
+
+    fn btype(&mut self) -> Result<TypeIdx, ParseError> {
+        let start = self.start();
+        let function = self.atype()?;
+        let mut arguments = Vector::new();
+        while self.starts_atype() {
+            arguments.push_back(self.atype()?);
+        }
+        if arguments.is_empty() {
+            Ok(function)
+        } else {
+            Ok(self.allocate_ty(
+                start,
+                Type::Application {
+                    function,
+                    arguments,
+                },
+            ))
+        }
+    }
+
+    fn atype(&mut self) -> Result<TypeIdx, ParseError> {
+        let start = self.start();
+        let ty = match self.peek().clone() {
+            TokenKind::Upper(name) => Type::Constructor { name },
+            TokenKind::Lower(name) => Type::Variable { name, rank: 0 },
+            TokenKind::LeftParen => {
+                let is_row = matches!(
+                    (self.peek_nth(1), self.peek_nth(2)),
+                    (TokenKind::RightParen | TokenKind::Pipe, _)
+                        | (TokenKind::Lower(_), TokenKind::DoubleColon)
+                );
+                if is_row {
+                    return self.row(TokenKind::LeftParen, TokenKind::RightParen);
+                }
+                self.bump();
+                let ty = self.ty()?;
+                self.expect(TokenKind::RightParen)?;
+                return Ok(ty);
+            }
+            TokenKind::LeftBrace => {
+                let row = self.row(TokenKind::LeftBrace, TokenKind::RightBrace)?;
+                return Ok(self.allocate_ty(start, Type::Record { row }));
+            }
+            _ => return Err(self.unexpected("a type")),
+        };
+        self.bump();
+        Ok(self.allocate_ty(start, ty))
+    }
+
+    fn row(&mut self, open: TokenKind, close: TokenKind) -> Result<TypeIdx, ParseError> {
+        let start = self.start();
+        self.expect(open)?;
+        let mut labels = Vector::new();
+        if let TokenKind::Lower(_) = self.peek() {
+            loop {
+                let label = self.lower()?;
+                self.expect(TokenKind::DoubleColon)?;
+                labels.push_back((label, self.ty()?));
+                if !self.eat(TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+        let tail = if self.eat(TokenKind::Pipe) {
+            Some(self.ty()?)
+        } else {
+            None
+        };
+        self.expect(close)?;
+        Ok(self.allocate_ty(start, Type::Row { labels, tail }))
+    }
+
+    fn binders(&mut self) -> Result<Vector<TypeVariableBinder>, ParseError> {
+        let mut binders = Vector::new();
+        loop {
+            match self.peek().clone() {
+                TokenKind::Lower(name) => {
+                    self.bump();
+                    binders.push_back(TypeVariableBinder { name, kind: None });
+                }
+                TokenKind::LeftParen => {
+                    self.bump();
+                    let name = self.lower()?;
+                    self.expect(TokenKind::DoubleColon)?;
+                    let kind = self.kind()?;
+                    self.expect(TokenKind::RightParen)?;
+                    binders.push_back(TypeVariableBinder {
+                        name,
+                        kind: Some(kind),
+                    });
+                }
+                _ => return Ok(binders),
+            }
+        }
+    }
+
+    fn kind(&mut self) -> Result<Kind, ParseError> {
+        let argument = match self.peek().clone() {
+            TokenKind::Upper(name) if name == "Row" => {
+                self.bump();
+                let kind = self.kind_atom()?;
+                Kind::Row(Box::new(kind))
+            }
+            _ => self.kind_atom()?,
+        };
+        if self.eat(TokenKind::Arrow) {
+            Ok(Kind::arrow(argument, self.kind()?))
+        } else {
+            Ok(argument)
+        }
+    }
+
+    fn kind_atom(&mut self) -> Result<Kind, ParseError> {
+        match self.peek().clone() {
+            TokenKind::Upper(name) if name == "Type" => {
+                self.bump();
+                Ok(Kind::Type)
+            }
+            TokenKind::LeftParen => {
+                self.bump();
+                let kind = self.kind()?;
+                self.expect(TokenKind::RightParen)?;
+                Ok(kind)
+            }
+            _ => Err(self.unexpected("a kind")),
+        }
+    }
+
+    fn free_variables(&self, ty: TypeIdx, bound: &mut Vec<SmolStr>, free: &mut Vec<SmolStr>) {
+        match &self.arenas.type_arena[ty] {
+            Type::Constructor { .. } | Type::Unification { .. } => (),
+            Type::Variable { name, .. } => {
+                if !bound.contains(name) && !free.contains(name) {
+                    free.push(name.clone());
+                }
+            }
+            Type::Function { arguments, result } => {
+                for argument in arguments {
+                    self.free_variables(*argument, bound, free);
+                }
+                self.free_variables(*result, bound, free);
+            }
+            Type::Application {
+                function,
+                arguments,
+            } => {
+                self.free_variables(*function, bound, free);
+                for argument in arguments {
+                    self.free_variables(*argument, bound, free);
+                }
+            }
+            Type::Forall { variables, ty, .. } => {
+                let length = bound.len();
+                bound.extend(variables.iter().map(|binder| binder.name.clone()));
+                self.free_variables(*ty, bound, free);
+                bound.truncate(length);
+            }
+            Type::Constrained { assertions, ty } => {
+                for assertion in assertions {
+                    for argument in &assertion.arguments {
+                        self.free_variables(*argument, bound, free);
+                    }
+                }
+                self.free_variables(*ty, bound, free);
+            }
+            Type::Row { labels, tail } => {
+                for (_, label) in labels {
+                    self.free_variables(*label, bound, free);
+                }
+                if let Some(tail) = tail {
+                    self.free_variables(*tail, bound, free);
+                }
+            }
+            Type::Record { row } => self.free_variables(*row, bound, free),
+        }
+    }
+
+    fn starts_atom(&self) -> bool {
+        matches!(
+            self.peek(),
+            TokenKind::Lower(_)
+                | TokenKind::Upper(_)
+                | TokenKind::Integer(_)
+                | TokenKind::Float(_)
+                | TokenKind::String(_)
+                | TokenKind::Char(_)
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::LeftParen
+                | TokenKind::LeftBrace
+        )
+    }
+
+    fn starts_pattern_atom(&self) -> bool {
+        self.peek() == &TokenKind::Underscore
+            || (self.starts_atom() && self.peek() != &TokenKind::LeftBrace)
+    }
+
+    fn starts_atype(&self) -> bool {
+        matches!(
+            self.peek(),
+            TokenKind::Lower(_) | TokenKind::Upper(_) | TokenKind::LeftParen | TokenKind::LeftBrace
+        )
+    }
+
+    fn lowers(&mut self) -> Vector<SmolStr> {
+        let mut names = Vector::new();
+        while let TokenKind::Lower(name) = self.peek() {
+            names.push_back(name.clone());
+            self.bump();
+        }
+        names
+    }
+
+    fn lower(&mut self) -> Result<SmolStr, ParseError> {
+        match self.peek().clone() {
+            TokenKind::Lower(name) => {
+                self.bump();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    fn upper(&mut self) -> Result<SmolStr, ParseError> {
+        match self.peek().clone() {
+            TokenKind::Upper(name) => {
+                self.bump();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("a capitalized name")),
+        }
+    }
+
+    fn allocate_expr(&mut self, start: usize, expr: Expr) -> ExprIdx {
+        let span = self.span(start);
+        let e_idx = self.arenas.expr_arena.allocate(expr);
+        self.arenas.expr_spans.insert(e_idx, span);
+        e_idx
+    }
+
+    fn allocate_ty(&mut self, start: usize, ty: Type) -> TypeIdx {
+        let span = self.span(start);
+        let t_idx = self.arenas.type_arena.allocate(ty);
+        self.arenas.type_spans.insert_if_missing(t_idx, span);
+        t_idx
+    }
+
+    /// The span from `start` to the end of the last token consumed.
+    fn span(&self, start: usize) -> Span {
+        let end = match self.index.checked_sub(1) {
+            Some(index) => self.tokens[index].end,
+            None => start,
+        };
+        Span {
+            file: self.file.clone(),
+            start,
+            end: end.max(start),
+        }
+    }
+
+    fn start(&self) -> usize {
+        self.tokens[self.index].start
+    }
+
+    fn peek(&self) -> &TokenKind {
+        &self.tokens[self.index].kind
+    }
+
+    fn peek_nth(&self, n: usize) -> &TokenKind {
+        let index = (self.index + n).min(self.tokens.len() - 1);
+        &self.tokens[index].kind
+    }
+
+    fn bump(&mut self) {
+        if self.index + 1 < self.tokens.len() {
+            self.index += 1;
+        }
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> bool {
+        if self.peek() == &kind {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<(), ParseError> {
+        if self.eat(kind.clone()) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&kind.describe()))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let token = &self.tokens[self.index];
+        ParseError::UnexpectedToken(
+            Span {
+                file: self.file.clone(),
+                start: token.start,
+                end: token.end,
+            },
+            expected.into(),
+            token.kind.describe(),
+        )
+    }
+}
+
+fn literal(kind: &TokenKind) -> Option<Literal> {
+    match kind {
+        TokenKind::Integer(value) => Some(Literal::Integer(*value)),
+        TokenKind::Float(value) => Some(Literal::Float(*value)),
+        TokenKind::String(value) => Some(Literal::String(value.clone())),
+        TokenKind::Char(value) => Some(Literal::Char(*value)),
+        TokenKind::True => Some(Literal::Boolean(true)),
+        TokenKind::False => Some(Literal::Boolean(false)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iwc_arena::{Arena, InternedArena};
+    use iwc_core_ast::{
+        expr::{pretty::pretty_print_expr_with_types, Expr},
+        span::Spans,
+        ty::{pretty::pretty_print_ty, Type},
+    };
+    use iwc_core_error::ParseError;
+
+    use crate::{parse_module, parse_type, Arenas};
+
+    #[test]
+    fn module_parsing() {
+        let mut expr_arena = Arena::<Expr>::new();
+        let type_arena = InternedArena::<Type>::new();
+        let expr_spans = Spans::new();
+        let type_spans = Spans::new();
+
+        let source = r#"
+data Maybe a = Nothing | Just a
+
+-- Signatures may come before or after their bindings.
+fromMaybe :: a -> Maybe a -> a
+fromMaybe default m = case m of { Nothing -> default; Just x -> x }
+
+moveX r = r { x = r.x }
+
+origin = { x: 0, y: 0.5 } :: { x :: Int, y :: Float }
+
+lengths :: forall (r :: Row Type). Eq { size :: Int | r } => Int -> String -> ()
+lengths = \_ignored -> let rec f = \n -> g n; g = \n -> f n in f 0
+"#;
+        let (module, errors) = parse_module(
+            "Main.iwc",
+            source,
+            Arenas {
+                expr_arena: &mut expr_arena,
+                type_arena: &type_arena,
+                expr_spans: &expr_spans,
+                type_spans: &type_spans,
+            },
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let data = &module.data[0];
+        assert_eq!(data.name, "Maybe");
+        assert_eq!(data.type_params.len(), 1);
+        assert_eq!(data.constructors.len(), 2);
+
+        let printed: Vec<_> = module
+            .values
+            .iter()
+            .map(|value| {
+                let signature = value
+                    .signature
+                    .map(|signature| pretty_print_ty(&type_arena, signature));
+                let value = pretty_print_expr_with_types(
+                    &expr_arena,
+                    value.value,
+                    &type_arena,
+                    &Default::default(),
+                );
+                (signature, value)
+            })
+            .collect();
+        assert_eq!(
+            printed,
+            [
+                (
+                    Some("forall a. a -> Maybe a -> a".into()),
+                    "\\default m -> case m of { Nothing -> default; Just x -> x }".into()
+                ),
+                (None, "\\r -> r { x = r.x }".into()),
+                (None, "{ x: 0, y: 0.5 } :: { x :: Int, y :: Float }".into()),
+                (
+                    Some(
+                        "forall (r :: (Row Type)). Eq { size :: Int | r } => Int -> String -> ()"
+                            .into()
+                    ),
+                    "\\_ignored -> let rec f = \\n -> g n; g = \\n -> f n in f 0".into()
+                ),
+            ]
+        );
+
+        let span = expr_spans.get(module.values[1].value).unwrap();
+        assert_eq!(&source[span.start..span.end], "moveX r = r { x = r.x }");
+    }
+
+    #[test]
+    fn parse_error_recovery() {
+        let mut expr_arena = Arena::<Expr>::new();
+        let type_arena = InternedArena::<Type>::new();
+        let expr_spans = Spans::new();
+        let type_spans = Spans::new();
+
+        let source = "
+identity x = x
+
+broken = \\ -> x
+  where
+
+missing :: Int
+
+class Broken a | b -> a
+
+constant = 'a' $ 1
+
+y :: Int
+y :: String
+y = 3
+
+identity y = y
+";
+        let (module, errors) = parse_module(
+            "Main.iwc",
+            source,
+            Arenas {
+                expr_arena: &mut expr_arena,
+                type_arena: &type_arena,
+                expr_spans: &expr_spans,
+                type_spans: &type_spans,
+            },
+        );
+
+        let names: Vec<_> = module.values.iter().map(|value| &value.name).collect();
+        assert_eq!(names, ["identity", "constant", "y"]);
+
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| {
+                let span = error.span();
+                &source[span.start..span.end]
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "$",
+                "->",
+                "b",
+                "y :: String",
+                "identity y = y",
+                "missing :: Int"
+            ]
+        );
+
+        let errors = parse_type(
+            "<type>",
+            "forall a. a ->",
+            Arenas {
+                expr_arena: &mut expr_arena,
+                type_arena: &type_arena,
+                expr_spans: &expr_spans,
+                type_spans: &type_spans,
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ParseError::UnexpectedToken(_, expected, found)]
+                if expected == "a type" && found == "the end of the declaration"
+        ));
+    }
+}